@@ -2,6 +2,7 @@ mod scenes;
 
 use core::App;
 use core::AppConfig;
+use core::ctrl::CtrlBinding;
 use core::nannou::event::Key;
 use core::scene::SceneBuilder;
 use scenes::hat::Hat;
@@ -42,7 +43,16 @@ fn config(nannou_app: &core::NannouApp) -> core::Model {
         .params_base_path(params_base_path)
         .audio_base_path(audio_base_path)
         .scenes(scenes())
+        .ctrl_binding(
+            CtrlBinding::new("size", "snare", "size")
+                .map_range((0., 1.), (50., 300.))
+                .smoothing(0.1),
+        )
         .build(nannou_app)
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(1);
+        })
 }
 
 fn main() {
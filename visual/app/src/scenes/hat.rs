@@ -1,38 +1,36 @@
 use core::Model;
+use core::clock::Clock;
+use core::modulation::{Decay, Length};
 use core::nannou::prelude::*;
 use core::scene::Scene;
 
-#[derive(Default)]
 pub struct Hat {
-    is_active: bool,
-    progress: f64,
+    decay: Decay,
+}
+
+impl Default for Hat {
+    fn default() -> Self {
+        Hat {
+            decay: Decay::new(Length::Seconds(0.1)),
+        }
+    }
 }
 
 impl Scene for Hat {
     fn invoke(&mut self) {
-        self.is_active = true;
-        self.progress = 0.;
+        self.decay.trigger();
     }
 
     fn stop(&mut self) {
-        self.is_active = false;
+        self.decay.stop();
     }
 
-    fn update(&mut self, update: &Update) {
-        if !self.is_active {
-            return;
-        }
-
-        let delta_seconds = update.since_last.secs();
-        self.progress += delta_seconds;
-
-        if self.progress > 0.1 {
-            self.stop();
-        }
+    fn update(&mut self, update: &Update, clock: &Clock) {
+        self.decay.update(update, clock);
     }
 
     fn draw(&self, app: &App, _model: &Model, draw: &Draw) {
-        if !self.is_active {
+        if !self.decay.is_active() {
             return;
         }
 
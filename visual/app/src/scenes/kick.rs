@@ -1,46 +1,56 @@
 use core::Model;
+use core::clock::Clock;
+use core::modulation::{Decay, Length};
 use core::nannou::prelude::*;
 use core::scene::Scene;
 
-#[derive(Default)]
 pub struct Kick {
-    is_active: bool,
-    progress: f64,
+    decay: Decay,
+}
+
+impl Default for Kick {
+    fn default() -> Self {
+        Kick {
+            decay: Decay::new(Length::Seconds(0.1)),
+        }
+    }
 }
 
 impl Scene for Kick {
     fn invoke(&mut self) {
-        self.is_active = true;
+        self.decay.trigger();
     }
 
     fn stop(&mut self) {
-        self.is_active = false;
-        self.progress = 0.;
+        self.decay.stop();
     }
 
-    fn update(&mut self, update: &Update) {
-        if !self.is_active {
-            return;
-        }
-
-        let delta_seconds = update.since_last.secs();
-        self.progress += delta_seconds;
-
-        if self.progress > 0.1 {
-            self.stop();
-        }
+    fn update(&mut self, update: &Update, clock: &Clock) {
+        self.decay.update(update, clock);
     }
 
     fn draw(&self, _app: &App, model: &Model, draw: &Draw) {
-        if !self.is_active {
+        if !self.decay.is_active() {
             return;
         }
 
-        let points = (0..50).map(|i| {
-            let x = i as f32;
-            let point = pt2(x - 20., x.sin() * model.freqscope()[i] as f32 / 4.) * 20.0;
-            (point, WHITE)
-        });
+        let waveform = model.waveform();
+        let points: Vec<_> = if waveform.is_empty() {
+            (0..50)
+                .map(|i| {
+                    let x = i as f32;
+                    let point = pt2(x - 20., x.sin() * model.spectrum().smoothed(i) * 24.) * 20.0;
+                    (point, WHITE)
+                })
+                .collect()
+        } else {
+            let step = 800. / waveform.len() as f32;
+            waveform
+                .iter()
+                .enumerate()
+                .map(|(i, sample)| (pt2(i as f32 * step - 400., sample * 200.), WHITE))
+                .collect()
+        };
         draw.polyline().weight(3.0).points_colored(points);
     }
 }
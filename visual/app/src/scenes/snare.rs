@@ -1,4 +1,7 @@
 use core::Model;
+use core::OscSender;
+use core::clock::Clock;
+use core::modulation::{Decay, Length};
 use core::nannou::prelude::*;
 use core::scene::Scene;
 use core::sound::AudioFile;
@@ -9,41 +12,43 @@ use serde::Deserialize;
 #[derive(Deserialize, Default)]
 struct Params {
     size: f32,
+    // Sample index sent to SuperDirt, its default sample when left out
+    n: Option<i32>,
 }
 
-#[derive(Default)]
 pub struct Snare {
-    is_active: bool,
-    progress: f64,
+    decay: Decay,
     key_counter: u32,
     params: Params,
+    osc_sender: Option<OscSender>,
+}
+
+impl Default for Snare {
+    fn default() -> Self {
+        Snare {
+            decay: Decay::new(Length::Seconds(0.1)),
+            key_counter: 0,
+            params: Params::default(),
+            osc_sender: None,
+        }
+    }
 }
 
 impl Scene for Snare {
     fn invoke(&mut self) {
-        self.is_active = true;
+        self.decay.trigger();
     }
 
     fn stop(&mut self) {
-        self.is_active = false;
-        self.progress = 0.;
+        self.decay.stop();
     }
 
-    fn update(&mut self, update: &Update) {
-        if !self.is_active {
-            return;
-        }
-
-        let delta_seconds = update.since_last.secs();
-        self.progress += delta_seconds;
-
-        if self.progress > 0.1 {
-            self.stop();
-        }
+    fn update(&mut self, update: &Update, clock: &Clock) {
+        self.decay.update(update, clock);
     }
 
     fn draw(&self, app: &App, _model: &Model, draw: &Draw) {
-        if !self.is_active {
+        if !self.decay.is_active() {
             return;
         }
 
@@ -56,7 +61,20 @@ impl Scene for Snare {
 
     fn key_pressed(&mut self, audio: Option<(&AudioFile, &OutputStreamHandle)>) {
         if self.key_counter == 0 {
-            audio.play().unwrap();
+            // Prefer SuperDirt when available so the snare goes through its mixer and effects
+            let played = match &self.osc_sender {
+                Some(sender) => {
+                    let mut properties = vec![("s", "sn".into())];
+                    if let Some(n) = self.params.n {
+                        properties.push(("n", n.into()));
+                    }
+                    sender.dirt_play(&properties)
+                }
+                None => audio.play(),
+            };
+            if let Err(e) = played {
+                eprintln!("Failed to play snare: {}", e);
+            }
             self.invoke();
         }
 
@@ -67,9 +85,14 @@ impl Scene for Snare {
         self.key_counter = 0;
     }
 
+    fn on_osc_sender(&mut self, sender: OscSender) {
+        self.osc_sender = Some(sender);
+    }
+
     fn on_params_update(&mut self, data: core::ParamsData) {
         if let Ok(data) = data.get::<Params>() {
             self.params.size = data.size;
+            self.params.n = data.n;
         }
     }
 }
@@ -0,0 +1,159 @@
+mod fft;
+mod input;
+mod wav;
+
+use crate::{
+    spectrum::{Band, BandLayout, Spectrum},
+    waveform::Waveform,
+};
+use color_eyre::Result;
+use fft::BandFft;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, SyncSender},
+    time::{Duration, Instant},
+};
+
+// Batches waiting for the next frame, about two seconds of audio at the default buffer size
+const BACKLOG: usize = 1024;
+// Analyzed frames are held back for this long after a `/freq` frame,
+// so the two never alternate in the spectrum and show up as spectral flux
+const FREQ_HOLD: Duration = Duration::from_millis(500);
+
+// Where the built-in analysis takes its audio from when no `/freq` is sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisSource {
+    // Default audio input device
+    Input,
+    // Decoded and looped in real time without playing it, for testing without an input device
+    WavFile(PathBuf),
+    // Sounds played locally through `AudioFile::play` or `SoundPlayable::play`
+    LocalPlayback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalyzerSettings {
+    // Samples per FFT, a power of two; frames overlap by half of it
+    pub fft_size: usize,
+    // Bands the FFT bins are summed into, laid out by `AppConfig::spectrum_layout`
+    pub bands: usize,
+}
+
+impl Default for AnalyzerSettings {
+    // The same resolution as `sc/freq.scd`
+    fn default() -> Self {
+        AnalyzerSettings {
+            fft_size: 2048,
+            bands: 64,
+        }
+    }
+}
+
+// Mono samples handed from an audio thread to the analyzer
+pub(crate) struct SampleBatch {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl SampleBatch {
+    pub(crate) fn from_interleaved(sample_rate: u32, channels: usize, samples: &[f32]) -> Self {
+        let channels = channels.max(1);
+
+        SampleBatch {
+            sample_rate,
+            samples: samples
+                .chunks(channels)
+                .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+                .collect(),
+        }
+    }
+
+    // Audio callbacks must never block, so the batch is dropped
+    // when the analyzer has fallen behind, e.g. while the visual is stalled
+    pub(crate) fn send_from_audio_thread(self, sender: &SyncSender<SampleBatch>) {
+        let _ = sender.try_send(self);
+    }
+}
+
+pub(crate) struct Analyzer {
+    receiver: Receiver<SampleBatch>,
+    fft: BandFft,
+    bands: Vec<Band>,
+    samples: Vec<f32>,
+    sample_rate: u32,
+    _input_stream: Option<input::InputStream>,
+}
+
+impl Analyzer {
+    // Starts capturing from `source`. For `LocalPlayback` the returned sender is to be passed
+    // to `sound::open_output` as its tap. No sender is kept here, so capture threads notice
+    // once the analyzer is dropped.
+    pub(crate) fn start(
+        settings: AnalyzerSettings,
+        layout: BandLayout,
+        source: &AnalysisSource,
+    ) -> Result<(Self, Option<SyncSender<SampleBatch>>)> {
+        let (sender, receiver) = mpsc::sync_channel(BACKLOG);
+
+        let mut analyzer = Analyzer {
+            receiver,
+            fft: BandFft::new(settings.fft_size)?,
+            bands: layout.bands(settings.bands),
+            samples: Vec::new(),
+            sample_rate: 0,
+            _input_stream: None,
+        };
+
+        let playback_tap = match source {
+            AnalysisSource::Input => {
+                analyzer._input_stream = Some(input::capture(sender)?);
+                None
+            }
+            AnalysisSource::WavFile(path) => {
+                wav::stream(path, sender)?;
+                None
+            }
+            AnalysisSource::LocalPlayback => Some(sender),
+        };
+
+        Ok((analyzer, playback_tap))
+    }
+
+    // Analyzes everything captured since the last frame into the spectrum, unless `/freq` frames
+    // received at `freq_received` are filling it, and keeps the captured signal in the waveform
+    pub(crate) fn process(
+        &mut self,
+        spectrum: &mut Spectrum,
+        waveform: &mut Waveform,
+        freq_received: Option<Instant>,
+    ) {
+        while let Ok(batch) = self.receiver.try_recv() {
+            if batch.sample_rate != self.sample_rate {
+                self.sample_rate = batch.sample_rate;
+                self.samples.clear();
+            }
+            waveform.push(batch.sample_rate, batch.samples.iter().copied());
+            self.samples.extend(batch.samples);
+        }
+
+        let fft_size = self.fft.size();
+        let hop = fft_size / 2;
+
+        // Skip what cannot be caught up on after a stall instead of lagging behind
+        if self.samples.len() > fft_size * 4 {
+            let excess = self.samples.len() - fft_size - hop;
+            self.samples.drain(..excess);
+        }
+
+        let held = freq_received.is_some_and(|v| v.elapsed() < FREQ_HOLD);
+        while self.samples.len() >= fft_size {
+            if !held {
+                let values =
+                    self.fft
+                        .band_db(&self.samples[..fft_size], self.sample_rate, &self.bands);
+                spectrum.set(values);
+            }
+            self.samples.drain(..hop);
+        }
+    }
+}
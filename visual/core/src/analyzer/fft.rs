@@ -0,0 +1,76 @@
+use crate::spectrum::Band;
+use color_eyre::{Result, eyre::bail};
+use rustfft::{Fft, FftPlanner, num_complex::Complex};
+use std::{f32::consts::TAU, sync::Arc};
+
+// Same floor as `sc/freq.scd`
+const MIN_DB: f32 = -96.;
+
+// Hann windowed FFT whose bin powers are summed into bands, in dB relative to a full scale sine
+pub(super) struct BandFft {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    window_sum: f32,
+    buffer: Vec<Complex<f32>>,
+}
+
+impl BandFft {
+    pub(super) fn new(size: usize) -> Result<Self> {
+        if size < 2 || !size.is_power_of_two() {
+            bail!("FFT size must be a power of two, got {}", size);
+        }
+
+        let window: Vec<f32> = (0..size)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f32 / size as f32).cos())
+            .collect();
+
+        Ok(BandFft {
+            fft: FftPlanner::new().plan_fft_forward(size),
+            window_sum: window.iter().sum(),
+            window,
+            buffer: vec![Complex::default(); size],
+        })
+    }
+
+    pub(super) fn size(&self) -> usize {
+        self.window.len()
+    }
+
+    pub(super) fn band_db(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+        bands: &[Band],
+    ) -> Vec<f32> {
+        for ((bin, sample), weight) in self.buffer.iter_mut().zip(samples).zip(&self.window) {
+            *bin = Complex::new(sample * weight, 0.);
+        }
+        self.fft.process(&mut self.buffer);
+
+        let bins = self.size() / 2 + 1;
+        let bin_width = sample_rate as f32 / self.size() as f32;
+        let power = |k: usize| (2. * self.buffer[k].norm() / self.window_sum).powi(2);
+
+        bands
+            .iter()
+            .map(|band| {
+                let nyquist = sample_rate as f32 / 2.;
+                if band.low >= nyquist {
+                    return MIN_DB;
+                }
+
+                let first = (band.low / bin_width).ceil() as usize;
+                let last = ((band.high / bin_width).ceil() as usize).min(bins);
+
+                // Bands narrower than a bin take the bin their center falls into
+                let total = if first < last {
+                    (first..last).map(power).sum::<f32>()
+                } else {
+                    power(((band.center() / bin_width).round() as usize).min(bins - 1))
+                };
+
+                (10. * total.log10()).max(MIN_DB)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,29 @@
+use super::SampleBatch;
+use color_eyre::{Result, eyre::WrapErr};
+use nannou_audio::{Buffer, Host, Stream};
+use std::sync::mpsc::SyncSender;
+
+pub(super) type InputStream = Stream<SyncSender<SampleBatch>>;
+
+// Captures the default input device until the returned stream is dropped
+pub(super) fn capture(sender: SyncSender<SampleBatch>) -> Result<InputStream> {
+    let stream = Host::new()
+        .new_input_stream(sender)
+        .capture(on_capture)
+        .build()
+        .wrap_err("Failed to open the audio input for analysis")?;
+    stream
+        .play()
+        .wrap_err("Failed to start the audio input for analysis")?;
+    println!(
+        "Analyzing audio input at {} Hz",
+        stream.cpal_config().sample_rate.0
+    );
+
+    Ok(stream)
+}
+
+fn on_capture(sender: &mut SyncSender<SampleBatch>, buffer: &Buffer) {
+    SampleBatch::from_interleaved(buffer.sample_rate(), buffer.channels(), buffer)
+        .send_from_audio_thread(sender);
+}
@@ -0,0 +1,51 @@
+use super::SampleBatch;
+use color_eyre::{Result, eyre::WrapErr};
+use rodio::{Decoder, Source as _};
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::mpsc::{SyncSender, TrySendError},
+    thread,
+    time::{Duration, Instant},
+};
+
+const BATCH_FRAMES: usize = 512;
+
+// Decodes `path` up front and feeds it to the analyzer in real time, looping forever
+pub(super) fn stream(path: &Path, sender: SyncSender<SampleBatch>) -> Result<()> {
+    let file =
+        File::open(path).wrap_err_with(|| format!("Failed to open {}", path.to_string_lossy()))?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .wrap_err_with(|| format!("Failed to decode {}", path.to_string_lossy()))?;
+
+    let sample_rate = decoder.sample_rate();
+    let channels = decoder.channels() as usize;
+    let samples: Vec<f32> = decoder.convert_samples().collect();
+    println!(
+        "Analyzing {} in a loop at {} Hz",
+        path.to_string_lossy(),
+        sample_rate
+    );
+
+    if samples.is_empty() {
+        return Ok(());
+    }
+
+    let batch_duration = Duration::from_secs_f64(BATCH_FRAMES as f64 / sample_rate as f64);
+    thread::spawn(move || {
+        let mut due = Instant::now();
+
+        for chunk in samples.chunks(BATCH_FRAMES * channels).cycle() {
+            let batch = SampleBatch::from_interleaved(sample_rate, channels, chunk);
+            if let Err(TrySendError::Disconnected(_)) = sender.try_send(batch) {
+                break;
+            }
+
+            due += batch_duration;
+            thread::sleep(due.saturating_duration_since(Instant::now()));
+        }
+    });
+
+    Ok(())
+}
@@ -1,17 +1,26 @@
 use std::{
     path::{Path, PathBuf},
     sync::mpsc,
+    time::Duration,
 };
 
 use crate::{
-    Model, ParamsData, draw, event,
-    osc::Osc,
+    Model,
+    analyzer::{AnalysisSource, Analyzer, AnalyzerSettings},
+    clock::{self, Clock},
+    ctrl::CtrlBinding,
+    draw, event,
+    orbit::Orbits,
+    osc::{self, Osc, OscSender, TcpFraming},
     params::start_watch_file,
     scene::{SceneBuilder, SceneManager},
+    sound,
+    spectrum::{self, AnalysisSettings, BandLayout, Spectrum},
     update,
+    waveform::{self, Waveform},
 };
+use color_eyre::{Result, eyre::bail};
 use nannou::App as NannouApp;
-use rodio::OutputStream;
 
 pub struct App;
 
@@ -27,11 +36,33 @@ impl App {
     }
 }
 
+struct OscListener {
+    address: String,
+    forward_to: Option<String>,
+}
+
 #[derive(Default)]
 pub struct AppConfig {
     params_base_path: Option<PathBuf>,
     audio_base_path: Option<PathBuf>,
     scenes: Vec<SceneBuilder>,
+    osc_listeners: Vec<OscListener>,
+    osc_tcp_listeners: Vec<(String, TcpFraming)>,
+    osc_timetag_offset: f64,
+    osc_backlog: Option<usize>,
+    osc_max_lookahead: Option<f64>,
+    osc_send_target: Option<String>,
+    osc_record_path: Option<PathBuf>,
+    osc_replay: Option<(PathBuf, f64)>,
+    ctrl_bindings: Vec<CtrlBinding>,
+    beats_per_cycle: Option<u32>,
+    spectrum_layout: Option<BandLayout>,
+    spectrum_analysis: Option<AnalysisSettings>,
+    onset_sensitivity: Option<f32>,
+    spectrum_history: Option<f32>,
+    waveform_length: Option<f32>,
+    analysis_source: Option<AnalysisSource>,
+    analyzer_settings: Option<AnalyzerSettings>,
 }
 
 impl AppConfig {
@@ -54,14 +85,186 @@ impl AppConfig {
         self
     }
 
-    pub fn build(self, app: &NannouApp) -> Model {
+    // Can be called several times to listen on multiple sockets at once
+    pub fn osc_listen(mut self, address: impl Into<String>) -> Self {
+        self.osc_listeners.push(OscListener {
+            address: address.into(),
+            forward_to: None,
+        });
+        self
+    }
+
+    // Sits between Tidal and SuperDirt: packets received on `address` are forwarded
+    // unchanged to `forward_to` (e.g. "127.0.0.1:57120") and also drive the scenes
+    pub fn osc_proxy(mut self, address: impl Into<String>, forward_to: impl Into<String>) -> Self {
+        self.osc_listeners.push(OscListener {
+            address: address.into(),
+            forward_to: Some(forward_to.into()),
+        });
+        self
+    }
+
+    pub fn osc_listen_tcp(mut self, address: impl Into<String>, framing: TcpFraming) -> Self {
+        self.osc_tcp_listeners.push((address.into(), framing));
+        self
+    }
+
+    pub fn osc_timetag_offset(mut self, offset_seconds: f64) -> Self {
+        self.osc_timetag_offset = offset_seconds;
+        self
+    }
+
+    pub fn osc_backlog(mut self, capacity: usize) -> Self {
+        self.osc_backlog = Some(capacity);
+        self
+    }
+
+    // Longest a bundled message is held for its timetag, 2 seconds by default.
+    // Later timetags, e.g. from a sender whose clock is ahead, fire after this long instead.
+    pub fn osc_max_lookahead(mut self, seconds: f64) -> Self {
+        self.osc_max_lookahead = Some(seconds);
+        self
+    }
+
+    // Hands scenes an `OscSender` targeting `address`, e.g. SuperDirt on "127.0.0.1:57120"
+    pub fn osc_send_to(mut self, address: impl Into<String>) -> Self {
+        self.osc_send_target = Some(address.into());
+        self
+    }
+
+    pub fn osc_record(mut self, path: impl AsRef<Path>) -> Self {
+        self.osc_record_path = Some(path.as_ref().into());
+        self
+    }
+
+    // Feeds a recording made with `osc_record` back in, `speed` times faster than it was recorded.
+    // No default listening socket is opened while replaying.
+    pub fn osc_replay(mut self, path: impl AsRef<Path>, speed: f64) -> Self {
+        self.osc_replay = Some((path.as_ref().into(), speed));
+        self
+    }
+
+    pub fn ctrl_binding(mut self, binding: CtrlBinding) -> Self {
+        self.ctrl_bindings.push(binding);
+        self
+    }
+
+    pub fn beats_per_cycle(mut self, beats: u32) -> Self {
+        self.beats_per_cycle = Some(beats);
+        self
+    }
+
+    // Frequency ranges of the bands received on `/freq`
+    pub fn spectrum_layout(mut self, layout: BandLayout) -> Self {
+        self.spectrum_layout = Some(layout);
+        self
+    }
+
+    pub fn spectrum_analysis(mut self, settings: AnalysisSettings) -> Self {
+        self.spectrum_analysis = Some(settings);
+        self
+    }
+
+    // Seconds of spectrum frames kept in `Spectrum::history`
+    pub fn spectrum_history(mut self, seconds: f32) -> Self {
+        self.spectrum_history = Some(seconds);
+        self
+    }
+
+    // Standard deviations above the recent spectral flux needed to count as an onset,
+    // lower values trigger `SceneBuilder::onset` scenes more easily
+    pub fn onset_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.onset_sensitivity = Some(sensitivity);
+        self
+    }
+
+    // Seconds of signal kept in `Model::waveform`
+    pub fn waveform_length(mut self, seconds: f32) -> Self {
+        self.waveform_length = Some(seconds);
+        self
+    }
+
+    // Fills the spectrum and the waveform from audio analyzed in-process, for running without `sc/freq.scd`.
+    // While `/freq` messages are received they fill the spectrum instead of the analyzed frames.
+    pub fn analyze_audio(mut self, source: AnalysisSource) -> Self {
+        self.analysis_source = Some(source);
+        self
+    }
+
+    pub fn analyzer_settings(mut self, settings: AnalyzerSettings) -> Self {
+        self.analyzer_settings = Some(settings);
+        self
+    }
+
+    pub fn build(self, app: &NannouApp) -> Result<Model> {
         app.new_window()
             .size(800, 600)
             .title("nannou OSC Visual")
             .build()
             .unwrap();
 
-        let osc = Osc::listen("0.0.0.0:2020");
+        if !self.osc_timetag_offset.is_finite() {
+            bail!(
+                "OSC timetag offset must be finite, got {}",
+                self.osc_timetag_offset
+            );
+        }
+
+        for scene in &self.scenes {
+            scene.validate()?;
+        }
+
+        // A zero capacity channel would only accept packets while the main thread is waiting
+        if self.osc_backlog == Some(0) {
+            bail!("OSC backlog must be at least 1");
+        }
+
+        for (name, seconds) in [
+            ("Spectrum history", self.spectrum_history),
+            ("Waveform length", self.waveform_length),
+        ] {
+            if let Some(seconds) = seconds
+                && !(seconds.is_finite() && seconds >= 0.)
+            {
+                bail!("{name} must be finite and non-negative, got {seconds}");
+            }
+        }
+
+        let max_lookahead = self.osc_max_lookahead.unwrap_or(osc::DEFAULT_MAX_LOOKAHEAD);
+        let Ok(max_lookahead) = Duration::try_from_secs_f64(max_lookahead) else {
+            bail!("OSC max lookahead must be finite and non-negative, got {max_lookahead}");
+        };
+
+        let mut osc = Osc::new(
+            self.osc_timetag_offset,
+            self.osc_backlog.unwrap_or(osc::DEFAULT_BACKLOG),
+            max_lookahead,
+        );
+
+        for binding in self.ctrl_bindings {
+            osc.bind_ctrl(binding);
+        }
+
+        if let Some(record_path) = &self.osc_record_path {
+            osc.record(record_path)?;
+        }
+
+        if self.osc_listeners.is_empty()
+            && self.osc_tcp_listeners.is_empty()
+            && self.osc_replay.is_none()
+        {
+            osc.listen(osc::DEFAULT_LISTEN_ADDRESS, None)?;
+        }
+        for listener in &self.osc_listeners {
+            osc.listen(&listener.address, listener.forward_to.as_deref())?;
+        }
+        for (address, framing) in &self.osc_tcp_listeners {
+            osc.listen_tcp(address, *framing)?;
+        }
+
+        if let Some((replay_path, speed)) = &self.osc_replay {
+            osc.replay(replay_path, *speed)?;
+        }
 
         let scenes = self
             .scenes
@@ -78,27 +281,68 @@ impl AppConfig {
                     start_watch_file(&full_params_path, tx);
 
                     let file_content = std::fs::read_to_string(&full_params_path).unwrap();
-                    scene
-                        .instance
-                        .on_params_update(ParamsData::new(file_content));
                     scene.params_update_event_rx = Some(rx);
-                    Some(scene.build(audio_base_path))
+
+                    let mut scene = scene.build(audio_base_path);
+                    scene.load_params(file_content);
+                    Some(scene)
                 } else {
                     None
                 }
             })
             .collect();
 
-        let scene_manager = SceneManager::new(scenes);
+        let mut scene_manager = SceneManager::new(scenes);
 
-        let (_stream, audio_handle) = OutputStream::try_default().unwrap();
+        if let Some(target) = &self.osc_send_target {
+            scene_manager.set_osc_sender(&OscSender::new(target)?);
+        }
+
+        let history_length = self
+            .spectrum_history
+            .unwrap_or(spectrum::DEFAULT_HISTORY_LENGTH);
+        let mut spectrum = Spectrum::new(
+            self.spectrum_layout.unwrap_or_default(),
+            self.spectrum_analysis.unwrap_or_default(),
+            history_length,
+        );
+        let onset_sensitivity = self
+            .onset_sensitivity
+            .unwrap_or(spectrum::DEFAULT_ONSET_SENSITIVITY);
+        for range in scene_manager.onset_ranges() {
+            spectrum.watch_onsets(range, onset_sensitivity);
+        }
 
-        Model {
+        let (analyzer, playback_tap) = match &self.analysis_source {
+            Some(source) => {
+                let (analyzer, playback_tap) = Analyzer::start(
+                    self.analyzer_settings.unwrap_or_default(),
+                    spectrum.layout(),
+                    source,
+                )?;
+                (Some(analyzer), playback_tap)
+            }
+            None => (None, None),
+        };
+        let (_stream, audio_handle) = sound::open_output(playback_tap)?;
+
+        Ok(Model {
             osc,
             scene_manager,
-            freqscope: [0; 1024],
+            spectrum,
+            orbits: Orbits::new(
+                self.spectrum_layout.unwrap_or_default(),
+                self.spectrum_analysis.unwrap_or_default(),
+                history_length,
+            ),
+            waveform: Waveform::new(self.waveform_length.unwrap_or(waveform::DEFAULT_LENGTH)),
+            analyzer,
+            clock: Clock::new(
+                self.beats_per_cycle
+                    .unwrap_or(clock::DEFAULT_BEATS_PER_CYCLE),
+            ),
             _audio_stream: _stream,
             audio_handle,
-        }
+        })
     }
 }
@@ -0,0 +1,87 @@
+use std::time::SystemTime;
+
+// Tidal's default tempo until the first event tells otherwise
+const DEFAULT_CPS: f64 = 0.5625;
+
+pub(crate) const DEFAULT_BEATS_PER_CYCLE: u32 = 4;
+
+// Tracks Tidal's tempo and cycle position from the `cps` and `cycle` of dirt events,
+// extrapolating between events so visuals can follow the beat without triggers
+pub struct Clock {
+    cps: f64,
+    beats_per_cycle: u32,
+    reference_cycle: f64,
+    reference_time: SystemTime,
+    cycle: f64,
+    synced: bool,
+}
+
+impl Clock {
+    pub(crate) fn new(beats_per_cycle: u32) -> Self {
+        Clock {
+            cps: DEFAULT_CPS,
+            beats_per_cycle: beats_per_cycle.max(1),
+            reference_cycle: 0.,
+            reference_time: SystemTime::now(),
+            cycle: 0.,
+            synced: false,
+        }
+    }
+
+    // `time` is when the event is due, i.e. its timetag rather than its arrival
+    pub(crate) fn sync(&mut self, cycle: f64, cps: f64, time: SystemTime) {
+        self.reference_cycle = cycle;
+        self.reference_time = time;
+        self.cps = cps;
+        self.synced = true;
+    }
+
+    pub(crate) fn tick(&mut self, now: SystemTime) {
+        let elapsed = match now.duration_since(self.reference_time) {
+            Ok(elapsed) => elapsed.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        };
+
+        self.cycle = self.reference_cycle + elapsed * self.cps;
+    }
+
+    // Whether a dirt event carrying the tempo has been received yet
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    pub fn cps(&self) -> f64 {
+        self.cps
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.cps * 60. * self.beats_per_cycle as f64
+    }
+
+    pub fn beats_per_cycle(&self) -> u32 {
+        self.beats_per_cycle
+    }
+
+    pub fn cycle(&self) -> f64 {
+        self.cycle
+    }
+
+    // A Tidal cycle counts as one bar
+    pub fn bar(&self) -> i64 {
+        self.cycle.floor() as i64
+    }
+
+    // Position within the current cycle in [0, 1)
+    pub fn cycle_phase(&self) -> f64 {
+        self.cycle.rem_euclid(1.)
+    }
+
+    pub fn beat(&self) -> u32 {
+        (self.cycle_phase() * self.beats_per_cycle as f64) as u32
+    }
+
+    // Position within the current beat in [0, 1)
+    pub fn beat_phase(&self) -> f64 {
+        (self.cycle_phase() * self.beats_per_cycle as f64).fract()
+    }
+}
@@ -0,0 +1,134 @@
+use crate::scene::SceneManager;
+use std::time::Instant;
+
+// Binds a Tidal controller bus value (`/ctrl name value`) to a scene parameter,
+// the same field that would otherwise be read from the scene's params file
+pub struct CtrlBinding {
+    name: String,
+    scene: String,
+    param: String,
+    input_range: (f64, f64),
+    output_range: Option<(f64, f64)>,
+    smoothing: f64,
+}
+
+impl CtrlBinding {
+    // `scene` is a scene name and may contain `*` and `?` wildcards
+    pub fn new(name: &str, scene: &str, param: &str) -> Self {
+        CtrlBinding {
+            name: name.to_owned(),
+            scene: scene.to_owned(),
+            param: param.to_owned(),
+            input_range: (0., 1.),
+            output_range: None,
+            smoothing: 0.,
+        }
+    }
+
+    // Maps `input` linearly onto `output`, clamping to `output`
+    pub fn map_range(mut self, input: (f64, f64), output: (f64, f64)) -> Self {
+        self.input_range = input;
+        self.output_range = Some(output);
+        self
+    }
+
+    // Time in seconds to get about two thirds of the way to a new value
+    pub fn smoothing(mut self, seconds: f64) -> Self {
+        self.smoothing = seconds;
+        self
+    }
+
+    fn map(&self, value: f64) -> f64 {
+        let Some((out_min, out_max)) = self.output_range else {
+            return value;
+        };
+        let (in_min, in_max) = self.input_range;
+
+        let t = if in_max == in_min {
+            0.
+        } else {
+            ((value - in_min) / (in_max - in_min)).clamp(0., 1.)
+        };
+
+        out_min + (out_max - out_min) * t
+    }
+}
+
+struct CtrlState {
+    current: f64,
+    target: f64,
+    dirty: bool,
+}
+
+pub(crate) struct CtrlMapper {
+    bindings: Vec<(CtrlBinding, Option<CtrlState>)>,
+    last_update: Instant,
+}
+
+impl CtrlMapper {
+    pub(crate) fn new() -> Self {
+        CtrlMapper {
+            bindings: Vec::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    pub(crate) fn add_binding(&mut self, binding: CtrlBinding) {
+        self.bindings.push((binding, None));
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: f64) {
+        for (binding, state) in &mut self.bindings {
+            if binding.name != name {
+                continue;
+            }
+
+            let target = binding.map(value);
+            match state {
+                Some(state) => {
+                    state.target = target;
+                    state.dirty = true;
+                }
+                None => {
+                    *state = Some(CtrlState {
+                        current: target,
+                        target,
+                        dirty: true,
+                    });
+                }
+            }
+        }
+    }
+
+    pub(crate) fn update(&mut self, scene_manager: &mut SceneManager) {
+        let delta_seconds = self.last_update.elapsed().as_secs_f64();
+        self.last_update = Instant::now();
+
+        for (binding, state) in &mut self.bindings {
+            let Some(state) = state else {
+                continue;
+            };
+
+            if state.current != state.target {
+                let amount = if binding.smoothing > 0. {
+                    1. - (-delta_seconds / binding.smoothing).exp()
+                } else {
+                    1.
+                };
+                state.current += (state.target - state.current) * amount;
+
+                if (state.target - state.current).abs() < 1e-6 {
+                    state.current = state.target;
+                }
+                state.dirty = true;
+            }
+
+            if state.dirty {
+                for scene in scene_manager.iter_mut_by_name(&binding.scene) {
+                    scene.set_number_param(&binding.param, state.current);
+                }
+                state.dirty = false;
+            }
+        }
+    }
+}
@@ -1,25 +1,56 @@
 pub use app::{App, AppConfig};
 pub use model::Model;
 pub use nannou::{self, App as NannouApp};
+pub use osc::{OscSender, OscStats, TcpFraming};
 pub use params::ParamsData;
 
+pub mod analyzer;
 mod app;
+pub mod clock;
+pub mod ctrl;
 mod model;
+pub mod modulation;
+pub mod orbit;
 mod osc;
 mod params;
 pub mod scene;
 pub mod sound;
+pub mod spectrum;
+pub mod waveform;
 
 use nannou::color::BLACK;
 use nannou::event::Update;
 use nannou::event::WindowEvent::{KeyPressed, KeyReleased};
+use nannou::time::DurationF64;
 use nannou::{Event, Frame};
+use osc::OscTargets;
+use std::time::SystemTime;
 
 fn update(_app: &NannouApp, model: &mut Model, update: Update) {
-    model.scene_manager.update_all(&update);
-    model
-        .osc
-        .handle_event(&mut model.freqscope, &mut model.scene_manager);
+    model.clock.tick(SystemTime::now());
+    if let Some(analyzer) = &mut model.analyzer {
+        analyzer.process(
+            &mut model.spectrum,
+            &mut model.waveform,
+            model.osc.freq_received(),
+        );
+    }
+    model.spectrum.update(update.since_last.secs() as f32);
+    model.orbits.update(update.since_last.secs() as f32);
+    model.scene_manager.update_all(&update, &model.clock);
+    model.osc.handle_event(OscTargets {
+        spectrum: &mut model.spectrum,
+        waveform: &mut model.waveform,
+        orbits: &mut model.orbits,
+        scene_manager: &mut model.scene_manager,
+        clock: &mut model.clock,
+    });
+
+    for onset in model.spectrum.take_onsets() {
+        for scene in model.scene_manager.iter_mut_by_onset(&onset) {
+            scene.instance.on_onset(&onset);
+        }
+    }
 }
 
 fn draw(app: &NannouApp, model: &Model, frame: Frame) {
@@ -39,14 +70,14 @@ fn event(_app: &NannouApp, model: &mut Model, event: Event) {
     {
         match window_event {
             KeyPressed(key) => {
-                if let Some(scene) = model.scene_manager.get_mut_by_key(key) {
+                for scene in model.scene_manager.iter_mut_by_key(key) {
                     scene
                         .instance
                         .key_pressed(scene.audio_file.as_ref().map(|f| (f, &model.audio_handle)));
                 }
             }
             KeyReleased(key) => {
-                if let Some(scene) = model.scene_manager.get_mut_by_key(key) {
+                for scene in model.scene_manager.iter_mut_by_key(key) {
                     scene
                         .instance
                         .key_released(scene.audio_file.as_ref().map(|f| (f, &model.audio_handle)));
@@ -1,16 +1,51 @@
-use crate::{osc::Osc, scene::SceneManager};
-use rodio::{OutputStream, OutputStreamHandle};
+use crate::{
+    analyzer::Analyzer,
+    clock::Clock,
+    orbit::{Level, Orbits},
+    osc::{Osc, OscStats},
+    scene::SceneManager,
+    sound::OutputStreamHandle,
+    spectrum::Spectrum,
+    waveform::Waveform,
+};
+use rodio::OutputStream;
 
 pub struct Model {
     pub(crate) osc: Osc,
     pub(crate) scene_manager: SceneManager,
-    pub(crate) freqscope: [i32; 1024],
+    pub(crate) spectrum: Spectrum,
+    pub(crate) waveform: Waveform,
+    pub(crate) orbits: Orbits,
+    pub(crate) analyzer: Option<Analyzer>,
+    pub(crate) clock: Clock,
     pub(crate) audio_handle: OutputStreamHandle,
     pub(crate) _audio_stream: OutputStream,
 }
 
 impl Model {
-    pub fn freqscope(&self) -> [i32; 1024] {
-        self.freqscope
+    pub fn spectrum(&self) -> &Spectrum {
+        &self.spectrum
+    }
+
+    // Spectrum received for a SuperDirt orbit (0 for d1) with `/freq orbit blob`
+    pub fn orbit_spectrum(&self, orbit: i32) -> Option<&Spectrum> {
+        self.orbits.spectrum(orbit)
+    }
+
+    // Level received for a SuperDirt orbit with `/level orbit rms peak`
+    pub fn orbit_level(&self, orbit: i32) -> Option<Level> {
+        self.orbits.level(orbit)
+    }
+
+    pub fn waveform(&self) -> &Waveform {
+        &self.waveform
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn osc_stats(&self) -> OscStats {
+        self.osc.stats()
     }
 }
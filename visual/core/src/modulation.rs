@@ -0,0 +1,233 @@
+use crate::clock::Clock;
+use nannou::{event::Update, rand, time::DurationF64};
+use std::f64::consts::TAU;
+
+// Lengths are either absolute or follow the Tidal tempo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Seconds(f64),
+    Cycles(f64),
+}
+
+impl Length {
+    pub fn seconds(self, clock: &Clock) -> f64 {
+        match self {
+            Length::Seconds(seconds) => seconds,
+            Length::Cycles(cycles) => cycles / clock.cps(),
+        }
+    }
+
+    // Fraction of this length covered by `delta_seconds`, 1 for zero lengths and for
+    // lengths in cycles while the tempo is 0, which would otherwise never finish
+    fn fraction(self, delta_seconds: f64, clock: &Clock) -> f64 {
+        let seconds = self.seconds(clock);
+        if seconds > 0. && seconds.is_finite() {
+            delta_seconds / seconds
+        } else {
+            1.
+        }
+    }
+}
+
+// Falls linearly from 1 to 0 over `length` after each trigger
+pub struct Decay {
+    length: Length,
+    progress: f64,
+    active: bool,
+}
+
+impl Decay {
+    pub fn new(length: Length) -> Self {
+        Decay {
+            length,
+            progress: 0.,
+            active: false,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.progress = 0.;
+        self.active = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+    }
+
+    pub fn update(&mut self, update: &Update, clock: &Clock) {
+        if !self.active {
+            return;
+        }
+
+        self.progress += self.length.fraction(update.since_last.secs(), clock);
+        if self.progress >= 1. {
+            self.stop();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn value(&self) -> f64 {
+        if self.active { 1. - self.progress } else { 0. }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+// ADSR envelope, held at `sustain` between `trigger` and `release`.
+// With a sustain level of 0 it finishes by itself, which suits one-shot dirt events.
+pub struct Envelope {
+    attack: Length,
+    decay: Length,
+    sustain: f64,
+    release: Length,
+    stage: Stage,
+    level: f64,
+    release_level: f64,
+}
+
+impl Envelope {
+    pub fn new(attack: Length, decay: Length, sustain: f64, release: Length) -> Self {
+        Envelope {
+            attack,
+            decay,
+            sustain: sustain.clamp(0., 1.),
+            release,
+            stage: Stage::Idle,
+            level: 0.,
+            release_level: 0.,
+        }
+    }
+
+    // Restarts the attack from the current level
+    pub fn trigger(&mut self) {
+        self.stage = Stage::Attack;
+    }
+
+    pub fn release(&mut self) {
+        if self.stage != Stage::Idle {
+            self.stage = Stage::Release;
+            self.release_level = self.level;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stage = Stage::Idle;
+        self.level = 0.;
+    }
+
+    pub fn update(&mut self, update: &Update, clock: &Clock) {
+        let delta_seconds = update.since_last.secs();
+
+        match self.stage {
+            Stage::Idle | Stage::Sustain => {}
+            Stage::Attack => {
+                self.level += self.attack.fraction(delta_seconds, clock);
+                if self.level >= 1. {
+                    self.level = 1.;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -= self.decay.fraction(delta_seconds, clock) * (1. - self.sustain);
+                if self.level <= self.sustain {
+                    self.level = self.sustain;
+                    self.stage = if self.sustain > 0. {
+                        Stage::Sustain
+                    } else {
+                        Stage::Idle
+                    };
+                }
+            }
+            Stage::Release => {
+                self.level -= self.release.fraction(delta_seconds, clock) * self.release_level;
+                if self.level <= 0. {
+                    self.stop();
+                }
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.stage != Stage::Idle
+    }
+
+    pub fn value(&self) -> f64 {
+        self.level
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    // A new random value held for each period
+    Random,
+}
+
+// Oscillates between 0 and 1, starting at 0 except for `Waveform::Random`. Periods in cycles stay locked to the
+// Tidal cycle grid, e.g. `Length::Cycles(0.25)` restarts on every beat of a 4 beat cycle.
+pub struct Lfo {
+    waveform: Waveform,
+    period: Length,
+    phase: f64,
+    random: f64,
+}
+
+impl Lfo {
+    pub fn new(waveform: Waveform, period: Length) -> Self {
+        Lfo {
+            waveform,
+            period,
+            phase: 0.,
+            random: rand::random(),
+        }
+    }
+
+    pub fn update(&mut self, update: &Update, clock: &Clock) {
+        let previous_period = self.phase.floor();
+
+        self.phase = match self.period {
+            Length::Cycles(cycles) if cycles > 0. => clock.cycle() / cycles,
+            _ => self.phase + self.period.fraction(update.since_last.secs(), clock),
+        };
+
+        if self.phase.floor() != previous_period {
+            self.random = rand::random();
+        }
+    }
+
+    // Position within the current period in [0, 1)
+    pub fn phase(&self) -> f64 {
+        self.phase.rem_euclid(1.)
+    }
+
+    pub fn value(&self) -> f64 {
+        let phase = self.phase();
+
+        match self.waveform {
+            Waveform::Sine => 0.5 - 0.5 * (TAU * phase).cos(),
+            Waveform::Triangle => 1. - (2. * phase - 1.).abs(),
+            Waveform::Saw => phase,
+            Waveform::Square => {
+                if phase < 0.5 {
+                    0.
+                } else {
+                    1.
+                }
+            }
+            Waveform::Random => self.random,
+        }
+    }
+}
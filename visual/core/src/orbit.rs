@@ -0,0 +1,72 @@
+use crate::spectrum::{AnalysisSettings, BandLayout, Spectrum};
+use std::{collections::BTreeMap, time::Instant};
+
+// Latest level of one orbit as linear amplitude, where 1 is full scale
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    pub rms: f32,
+    pub peak: f32,
+    pub received: Instant,
+}
+
+impl Level {
+    pub fn rms_db(&self) -> f32 {
+        20. * self.rms.log10()
+    }
+
+    pub fn peak_db(&self) -> f32 {
+        20. * self.peak.log10()
+    }
+}
+
+// Spectra and levels of SuperDirt orbits, each created with the first message for its orbit
+pub(crate) struct Orbits {
+    spectra: BTreeMap<i32, Spectrum>,
+    levels: BTreeMap<i32, Level>,
+    layout: BandLayout,
+    settings: AnalysisSettings,
+    history_length: f32,
+}
+
+impl Orbits {
+    pub(crate) fn new(layout: BandLayout, settings: AnalysisSettings, history_length: f32) -> Self {
+        Orbits {
+            spectra: BTreeMap::new(),
+            levels: BTreeMap::new(),
+            layout,
+            settings,
+            history_length,
+        }
+    }
+
+    pub(crate) fn spectrum_mut(&mut self, orbit: i32) -> &mut Spectrum {
+        self.spectra
+            .entry(orbit)
+            .or_insert_with(|| Spectrum::new(self.layout, self.settings, self.history_length))
+    }
+
+    pub(crate) fn spectrum(&self, orbit: i32) -> Option<&Spectrum> {
+        self.spectra.get(&orbit)
+    }
+
+    pub(crate) fn set_level(&mut self, orbit: i32, rms: f32, peak: f32) {
+        self.levels.insert(
+            orbit,
+            Level {
+                rms,
+                peak,
+                received: Instant::now(),
+            },
+        );
+    }
+
+    pub(crate) fn level(&self, orbit: i32) -> Option<Level> {
+        self.levels.get(&orbit).copied()
+    }
+
+    pub(crate) fn update(&mut self, delta_seconds: f32) {
+        for spectrum in self.spectra.values_mut() {
+            spectrum.update(delta_seconds);
+        }
+    }
+}
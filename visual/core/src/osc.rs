@@ -1,42 +1,221 @@
-use crate::{scene::SceneManager, sound::DirtSound};
-use rosc::{OscMessage, OscPacket, OscType};
+mod control;
+mod proxy;
+mod recording;
+mod scheduler;
+mod sender;
+mod sink;
+mod stats;
+mod tcp;
+
+pub use sender::OscSender;
+pub use stats::OscStats;
+pub use tcp::TcpFraming;
+
+use crate::{
+    clock::Clock,
+    ctrl::{CtrlBinding, CtrlMapper},
+    orbit::Orbits,
+    scene::SceneManager,
+    sound::{DirtEvent, osc_number},
+    spectrum::Spectrum,
+    waveform::Waveform,
+};
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail, eyre},
+};
+use proxy::Proxy;
+use recording::{Recorder, Recording};
+use rosc::{OscMessage, OscPacket, OscType, address::OscAddress};
+use scheduler::Scheduler;
+use sink::PacketSink;
+use stats::OscStatsTracker;
 use std::{
     collections::HashMap,
-    net::{ToSocketAddrs, UdpSocket},
-    sync::mpsc::{self, Receiver, Sender},
+    io::ErrorKind,
+    net::{TcpListener, ToSocketAddrs, UdpSocket},
+    path::Path,
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, SyncSender},
+    },
     thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 type OscProps = HashMap<String, OscType>;
 
+pub(crate) const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0:2020";
+pub(crate) const DEFAULT_BACKLOG: usize = 1024;
+pub(crate) const DEFAULT_MAX_LOOKAHEAD: f64 = 2.;
+// Assumed for `/wave` samples until a message carries a sample rate
+const DEFAULT_WAVE_SAMPLE_RATE: u32 = 48000;
+
+// What incoming messages act on, borrowed from the model for one frame
+pub(crate) struct OscTargets<'a> {
+    pub spectrum: &'a mut Spectrum,
+    pub waveform: &'a mut Waveform,
+    pub orbits: &'a mut Orbits,
+    pub scene_manager: &'a mut SceneManager,
+    pub clock: &'a mut Clock,
+}
+
 pub(crate) struct Osc {
     pub receiver: Receiver<OscPacket>,
+    sender: SyncSender<OscPacket>,
+    scheduler: Scheduler,
+    stats: OscStatsTracker,
+    recorder: Option<Arc<Recorder>>,
+    ctrl: CtrlMapper,
+    freq_received: Option<Instant>,
 }
 
 impl Osc {
-    pub fn listen<A>(address: A) -> Self
-    where
-        A: ToSocketAddrs + 'static + Send,
-    {
-        let (sender, receiver) = mpsc::channel();
+    // Packets arriving while `backlog` packets are still waiting for the next frame are dropped
+    pub fn new(timetag_offset: f64, backlog: usize, max_lookahead: Duration) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(backlog);
+
+        Osc {
+            receiver,
+            sender,
+            scheduler: Scheduler::new(timetag_offset, max_lookahead),
+            stats: OscStatsTracker::new(),
+            recorder: None,
+            ctrl: CtrlMapper::new(),
+            freq_received: None,
+        }
+    }
+
+    // With `forward_to`, every received packet is also passed on unchanged to that address
+    pub fn listen(&mut self, address: &str, forward_to: Option<&str>) -> Result<()> {
+        let proxy = match forward_to {
+            Some(target) => {
+                let target = target
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addrs| addrs.next())
+                    .ok_or_else(|| eyre!("Invalid OSC forward address {}", target))?;
+                Some(Proxy::new(target))
+            }
+            None => None,
+        };
+
+        let socket = UdpSocket::bind(address)
+            .wrap_err_with(|| format!("Failed to bind OSC socket on {}", address))?;
+        println!("Listening for OSC on {}", socket.local_addr()?);
+        if let Some(target) = forward_to {
+            println!("Forwarding OSC to {}", target);
+        }
 
+        let sink = self.sink();
         thread::spawn(move || {
-            Osc::osc_receive_thread(sender, address);
+            Osc::osc_receive_thread(socket, proxy, sink);
         });
 
-        Osc { receiver }
+        Ok(())
     }
 
-    fn osc_receive_thread(osc_sender: Sender<OscPacket>, address: impl ToSocketAddrs) {
-        let socket = UdpSocket::bind(address).unwrap();
-        println!("Listening for OSC on {}", socket.local_addr().unwrap());
+    // Accepts any number of clients, each sending packets framed as `framing`
+    pub fn listen_tcp(&mut self, address: &str, framing: TcpFraming) -> Result<()> {
+        let listener = TcpListener::bind(address)
+            .wrap_err_with(|| format!("Failed to bind OSC TCP socket on {}", address))?;
+        println!(
+            "Listening for OSC over TCP on {} ({:?})",
+            listener.local_addr()?,
+            framing
+        );
+
+        let sink = self.sink();
+        thread::spawn(move || {
+            tcp::accept_thread(listener, framing, sink);
+        });
+
+        Ok(())
+    }
+
+    // Packets received by sources started after this call are written to `path`
+    pub fn record(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.recorder = Some(Arc::new(Recorder::create(&path)?));
+        println!("Recording OSC to {}", path.as_ref().to_string_lossy());
+
+        Ok(())
+    }
+
+    pub fn replay(&mut self, path: impl AsRef<Path>, speed: f64) -> Result<()> {
+        if !(speed.is_finite() && speed > 0.) {
+            bail!(
+                "OSC replay speed must be positive and finite, got {}",
+                speed
+            );
+        }
+
+        let recording = Recording::open(&path)?;
+        println!("Replaying OSC from {}", path.as_ref().to_string_lossy());
+
+        // Replayed packets are not recorded again
+        let sink = PacketSink::new(self.sender.clone(), self.stats.counters(), None);
+        thread::spawn(move || {
+            recording.replay(speed, |packet| {
+                sink.receive_decoded(packet);
+            });
+            println!("OSC replay finished");
+        });
+
+        Ok(())
+    }
+
+    fn sink(&self) -> PacketSink {
+        PacketSink::new(
+            self.sender.clone(),
+            self.stats.counters(),
+            self.recorder.clone(),
+        )
+    }
+
+    pub(crate) fn bind_ctrl(&mut self, binding: CtrlBinding) {
+        self.ctrl.add_binding(binding);
+    }
+
+    // When the last `/freq` frame for the main spectrum was received
+    pub(crate) fn freq_received(&self) -> Option<Instant> {
+        self.freq_received
+    }
+
+    pub(crate) fn stats(&self) -> OscStats {
+        OscStats {
+            clamped_timetags: self.scheduler.clamped(),
+            dropped_scheduled: self.scheduler.dropped(),
+            ..self.stats.stats()
+        }
+    }
+
+    fn osc_receive_thread(socket: UdpSocket, mut proxy: Option<Proxy>, sink: PacketSink) {
+        let mut buf = [0u8; 65507];
 
         loop {
-            let mut buf = [0u8; 65507];
             match socket.recv_from(&mut buf) {
-                Ok((_size, _addr)) => {
-                    let (_, packet) = rosc::decoder::decode_udp(&buf).unwrap();
-                    osc_sender.send(packet).unwrap();
+                Ok((size, addr)) => {
+                    if let Some(proxy) = &mut proxy
+                        && !proxy.relay(&socket, &buf[..size], addr)
+                    {
+                        continue;
+                    }
+
+                    if !sink.receive(&buf[..size], addr) {
+                        break;
+                    }
+                }
+                // Replies to the proxy's sends may surface here, e.g. ConnectionReset on Windows
+                // while SuperDirt is down, and must not stop the listener
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::ConnectionReset
+                            | ErrorKind::ConnectionRefused
+                            | ErrorKind::Interrupted
+                    ) =>
+                {
+                    eprintln!("Error receiving from socket, continuing: {}", e);
                 }
                 Err(e) => {
                     println!("Error receiving from socket: {}", e);
@@ -79,46 +258,158 @@ impl Osc {
         properties
     }
 
-    pub(crate) fn handle_freq(&mut self, msg: &OscMessage, freqscope: &mut [i32; 1024]) {
-        if let OscType::Blob(a) = &msg.args[0] {
-            freqscope
-                .iter_mut()
-                .zip(a.iter().map(|&v| v as i32 - 160))
-                .for_each(|(dst, src)| *dst = src);
+    // `sc/freq.scd` sends one Int8 dB value per band packed into a blob,
+    // plain numeric arguments are accepted as well.
+    // `/freq orbit blob` carries the spectrum of a single SuperDirt orbit.
+    pub(crate) fn handle_freq(
+        &mut self,
+        msg: &OscMessage,
+        spectrum: &mut Spectrum,
+        orbits: &mut Orbits,
+    ) {
+        let blob_db = |blob: &[u8]| blob.iter().map(|&v| v as i8 as f32).collect::<Vec<_>>();
+
+        match msg.args.as_slice() {
+            [OscType::Blob(blob)] => {
+                spectrum.set(blob_db(blob));
+                self.freq_received = Some(Instant::now());
+            }
+            [OscType::Int(orbit), OscType::Blob(blob)] => {
+                orbits.spectrum_mut(*orbit).set(blob_db(blob))
+            }
+            args => {
+                let values: Vec<f32> = args
+                    .iter()
+                    .filter_map(osc_number)
+                    .map(|v| v as f32)
+                    .collect();
+                if !values.is_empty() {
+                    spectrum.set(values);
+                    self.freq_received = Some(Instant::now());
+                }
+            }
+        }
+    }
+
+    // `/level orbit rms peak` with linear amplitudes, as sent by `sc/orbits.scd`
+    pub(crate) fn handle_level(&mut self, msg: &OscMessage, orbits: &mut Orbits) {
+        if let [OscType::Int(orbit), rms, peak] = msg.args.as_slice()
+            && let (Some(rms), Some(peak)) = (osc_number(rms), osc_number(peak))
+        {
+            orbits.set_level(*orbit, rms as f32, peak as f32);
+        }
+    }
+
+    // `sc/wave.scd` sends `/wave sample_rate blob` with one Int8 sample per byte,
+    // the sample rate may be left out and plain numeric samples in -1..1 are accepted as well
+    pub(crate) fn handle_wave(&mut self, msg: &OscMessage, waveform: &mut Waveform) {
+        let (sample_rate, args) = match msg.args.split_first() {
+            Some((&OscType::Int(sample_rate), args)) if sample_rate > 0 => {
+                (sample_rate as u32, args)
+            }
+            _ if waveform.sample_rate() > 0 => (waveform.sample_rate(), msg.args.as_slice()),
+            _ => (DEFAULT_WAVE_SAMPLE_RATE, msg.args.as_slice()),
+        };
+
+        match args {
+            [OscType::Blob(blob)] => {
+                waveform.push(sample_rate, blob.iter().map(|&v| v as i8 as f32 / 128.))
+            }
+            args => {
+                let samples: Vec<f32> = args
+                    .iter()
+                    .filter_map(osc_number)
+                    .map(|v| v as f32)
+                    .collect();
+                if !samples.is_empty() {
+                    waveform.push(sample_rate, samples);
+                }
+            }
+        }
+    }
+
+    // Tidal's controller bus sends `/ctrl name value` where name may also be an integer
+    pub(crate) fn handle_ctrl(&mut self, msg: &OscMessage) {
+        let name = match msg.args.first() {
+            Some(OscType::String(name)) => name.clone(),
+            Some(OscType::Int(name)) => name.to_string(),
+            _ => return,
+        };
+
+        if let Some(value) = msg.args.get(1).and_then(osc_number) {
+            self.ctrl.set(&name, value);
         }
     }
 
-    pub(crate) fn handle_dirt(&mut self, msg: &OscMessage, scenes: &mut SceneManager) {
+    pub(crate) fn handle_dirt(
+        &mut self,
+        msg: &OscMessage,
+        time: SystemTime,
+        scenes: &mut SceneManager,
+        clock: &mut Clock,
+    ) {
         let osc_properties = Osc::parse_properties(&msg.args);
 
-        if let Some(OscType::String(t)) = osc_properties.get("s") {
-            if let Some(scene) = scenes.get_mut_by_dirt_sound(&DirtSound::new(t)) {
-                scene.instance.invoke();
+        if let Some(event) = DirtEvent::from_properties(osc_properties) {
+            if let (Some(cycle), Some(cps)) = (event.cycle, event.cps) {
+                clock.sync(cycle, cps, time);
+            }
+
+            for scene in scenes.iter_mut_by_dirt_event(&event) {
+                scene.instance.on_dirt_event(&event);
             }
         }
     }
 
-    pub(crate) fn handle_event(
+    pub(crate) fn handle_message(
         &mut self,
-        freqscope: &mut [i32; 1024],
-        scene_manager: &mut SceneManager,
+        msg: &OscMessage,
+        time: SystemTime,
+        targets: &mut OscTargets,
     ) {
-        if let Ok(packet) = self.receiver.try_recv() {
-            match packet {
-                OscPacket::Bundle(bundle) => {
-                    if let OscPacket::Message(msg) = &bundle.content[0] {
-                        if msg.addr == "/dirt/play" {
-                            self.handle_dirt(msg, scene_manager);
-                        }
-                    }
+        let mut subscribed = false;
+        if let Ok(address) = OscAddress::new(msg.addr.clone()) {
+            for scene in targets.scene_manager.iter_mut_by_osc_address(&address) {
+                scene.instance.on_osc_message(msg);
+                subscribed = true;
+            }
+        }
+
+        match msg.addr.as_str() {
+            "/dirt/play" => self.handle_dirt(msg, time, targets.scene_manager, targets.clock),
+            "/freq" => self.handle_freq(msg, targets.spectrum, targets.orbits),
+            "/level" => self.handle_level(msg, targets.orbits),
+            "/wave" => self.handle_wave(msg, targets.waveform),
+            "/ctrl" => self.handle_ctrl(msg),
+            addr if addr.starts_with(control::PREFIX) => {
+                if !control::handle_control(msg, targets.scene_manager) {
+                    eprintln!("Warning: Unknown or malformed control message: {}", msg);
+                    self.stats.unknown_address();
                 }
+            }
+            _ if subscribed => {}
+            _ => self.stats.unknown_address(),
+        }
+    }
+
+    pub(crate) fn handle_event(&mut self, mut targets: OscTargets) {
+        while let Ok(packet) = self.receiver.try_recv() {
+            match packet {
+                OscPacket::Bundle(bundle) => self.scheduler.schedule_bundle(bundle),
 
                 OscPacket::Message(msg) => {
-                    if msg.addr == "/freq" {
-                        self.handle_freq(&msg, freqscope);
-                    }
+                    self.handle_message(&msg, SystemTime::now(), &mut targets);
                 }
             };
         }
+
+        self.stats.update();
+
+        let now = SystemTime::now();
+        while let Some((time, msg)) = self.scheduler.pop_due(now) {
+            self.handle_message(&msg, time, &mut targets);
+        }
+
+        self.ctrl.update(targets.scene_manager);
     }
 }
@@ -0,0 +1,94 @@
+use crate::{scene::SceneManager, sound::osc_number};
+use rosc::{OscMessage, OscType};
+
+pub(crate) const PREFIX: &str = "/compwaves/";
+
+// Handles the `/compwaves/...` namespace used to steer the visual remotely.
+// Scene names accept `*` and `?` wildcards, and flags default to on when omitted:
+//
+//   /compwaves/scene/invoke <name>
+//   /compwaves/scene/stop <name>
+//   /compwaves/scene/enable <name> [on]
+//   /compwaves/scene/mute <name> [on]
+//   /compwaves/scene/param <name> <key> <value>
+//   /compwaves/bank <bank>
+//   /compwaves/blackout [on]
+//
+// Returns `false` for unknown commands or malformed arguments
+pub(crate) fn handle_control(msg: &OscMessage, scene_manager: &mut SceneManager) -> bool {
+    let command = &msg.addr[PREFIX.len()..];
+    let args = msg.args.as_slice();
+
+    match (command, args) {
+        ("scene/invoke", [OscType::String(name)]) => {
+            for scene in scene_manager.iter_mut_by_name(name) {
+                scene.instance.invoke();
+            }
+        }
+        ("scene/stop", [OscType::String(name)]) => {
+            for scene in scene_manager.iter_mut_by_name(name) {
+                scene.instance.stop();
+            }
+        }
+        ("scene/enable", [OscType::String(name), flag @ ..]) => {
+            let Some(enabled) = parse_flag(flag) else {
+                return false;
+            };
+            for scene in scene_manager.iter_mut_by_name(name) {
+                scene.set_enabled(enabled);
+            }
+        }
+        ("scene/mute", [OscType::String(name), flag @ ..]) => {
+            let Some(muted) = parse_flag(flag) else {
+                return false;
+            };
+            for scene in scene_manager.iter_mut_by_name(name) {
+                scene.set_enabled(!muted);
+            }
+        }
+        ("scene/param", [OscType::String(name), OscType::String(key), value]) => {
+            let Some(value) = to_toml_value(value) else {
+                return false;
+            };
+            for scene in scene_manager.iter_mut_by_name(name) {
+                scene.set_param(key, value.clone());
+            }
+        }
+        ("bank", [bank]) => {
+            let Some(bank) = osc_number(bank).filter(|&v| v >= 0.) else {
+                return false;
+            };
+            scene_manager.set_active_bank(bank as u32);
+        }
+        ("blackout", flag) => {
+            let Some(blackout) = parse_flag(flag) else {
+                return false;
+            };
+            scene_manager.set_blackout(blackout);
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+fn parse_flag(args: &[OscType]) -> Option<bool> {
+    match args {
+        [] => Some(true),
+        [OscType::Bool(v)] => Some(*v),
+        [v] => osc_number(v).map(|v| v != 0.),
+        _ => None,
+    }
+}
+
+fn to_toml_value(value: &OscType) -> Option<toml::Value> {
+    match value {
+        OscType::Int(v) => Some(toml::Value::Integer(*v as i64)),
+        OscType::Long(v) => Some(toml::Value::Integer(*v)),
+        OscType::Float(v) => Some(toml::Value::Float(*v as f64)),
+        OscType::Double(v) => Some(toml::Value::Float(*v)),
+        OscType::String(v) => Some(toml::Value::String(v.clone())),
+        OscType::Bool(v) => Some(toml::Value::Boolean(*v)),
+        _ => None,
+    }
+}
@@ -0,0 +1,87 @@
+use std::net::{SocketAddr, UdpSocket};
+
+// Forwards every datagram to `target` unchanged and sends replies from `target`
+// (e.g. SuperDirt's handshake) back to the client that talked to us last
+pub(crate) struct Proxy {
+    target: SocketAddr,
+    client: Option<SocketAddr>,
+}
+
+impl Proxy {
+    pub(crate) fn new(target: SocketAddr) -> Self {
+        Proxy {
+            target,
+            client: None,
+        }
+    }
+
+    // Returns `true` when the datagram came from the client and should also be handled locally
+    pub(crate) fn relay(&mut self, socket: &UdpSocket, data: &[u8], from: SocketAddr) -> bool {
+        if from == self.target {
+            if let Some(client) = self.client
+                && let Err(e) = socket.send_to(data, client)
+            {
+                eprintln!("Failed to relay OSC reply to {}: {}", client, e);
+            }
+            return false;
+        }
+
+        self.client = Some(from);
+        if let Err(e) = socket.send_to(data, self.target) {
+            eprintln!("Failed to forward OSC packet to {}: {}", self.target, e);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn local_socket() -> UdpSocket {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        socket
+    }
+
+    fn receive(socket: &UdpSocket) -> (Vec<u8>, SocketAddr) {
+        let mut buf = [0u8; 64];
+        let (size, from) = socket.recv_from(&mut buf).unwrap();
+        (buf[..size].to_vec(), from)
+    }
+
+    #[test]
+    fn forwards_packets_and_routes_replies_back() {
+        let listener = local_socket();
+        let target = local_socket();
+        let client = local_socket();
+        let mut proxy = Proxy::new(target.local_addr().unwrap());
+
+        client
+            .send_to(b"/dirt/play", listener.local_addr().unwrap())
+            .unwrap();
+        let (data, from) = receive(&listener);
+        assert!(proxy.relay(&listener, &data, from));
+        assert_eq!(
+            receive(&target),
+            (b"/dirt/play".to_vec(), listener.local_addr().unwrap())
+        );
+
+        target
+            .send_to(b"/dirt/handshake/reply", listener.local_addr().unwrap())
+            .unwrap();
+        let (data, from) = receive(&listener);
+        assert!(!proxy.relay(&listener, &data, from));
+        assert_eq!(
+            receive(&client),
+            (
+                b"/dirt/handshake/reply".to_vec(),
+                listener.local_addr().unwrap()
+            )
+        );
+    }
+}
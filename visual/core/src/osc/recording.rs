@@ -0,0 +1,229 @@
+use super::scheduler::timetag_time;
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use rosc::{OscPacket, OscTime};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// A recording starts with this header followed by one record per packet:
+// arrival time as f64 seconds since the UNIX epoch, the packet length as u32
+// and the raw packet bytes, all little endian. Timetags stay inside the packet bytes.
+const MAGIC: &[u8; 8] = b"CWOSC001";
+
+pub(crate) struct Recorder(Mutex<BufWriter<File>>);
+
+impl Recorder {
+    pub(crate) fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = BufWriter::new(
+            File::create(path)
+                .wrap_err_with(|| format!("Failed to create OSC recording {}", path.display()))?,
+        );
+        file.write_all(MAGIC)?;
+        file.flush()?;
+
+        Ok(Recorder(Mutex::new(file)))
+    }
+
+    pub(crate) fn record(&self, data: &[u8], arrival: SystemTime) {
+        let arrival = arrival
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        let mut file = self.0.lock().unwrap();
+        let result = file
+            .write_all(&arrival.to_le_bytes())
+            .and_then(|_| file.write_all(&(data.len() as u32).to_le_bytes()))
+            .and_then(|_| file.write_all(data))
+            .and_then(|_| file.flush());
+
+        if let Err(e) = result {
+            eprintln!("Failed to write OSC recording: {}", e);
+        }
+    }
+}
+
+pub(crate) struct Recording {
+    records: Vec<(f64, Vec<u8>)>,
+}
+
+impl Recording {
+    pub(crate) fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = BufReader::new(
+            File::open(path)
+                .wrap_err_with(|| format!("Failed to open OSC recording {}", path.display()))?,
+        );
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("{} is not an OSC recording", path.display());
+        }
+
+        let mut records = Vec::new();
+        loop {
+            match read_record(&mut file) {
+                Ok(Some(record)) => records.push(record),
+                Ok(None) => break,
+                // The last record is cut off when the app was stopped while writing it
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    eprintln!(
+                        "Warning: Ignoring truncated last record of OSC recording {}",
+                        path.display()
+                    );
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(Recording { records })
+    }
+
+    // Calls `emit` with every recorded packet at its original pace divided by `speed`.
+    // Bundle timetags are moved to the replay time, keeping their distance to the arrival time.
+    pub(crate) fn replay(&self, speed: f64, mut emit: impl FnMut(Option<OscPacket>)) {
+        let Some((first_arrival, _)) = self.records.first() else {
+            return;
+        };
+        let start = Instant::now();
+
+        for (arrival, data) in &self.records {
+            // Corrupt arrival times are replayed right away instead of panicking
+            let delay = Duration::try_from_secs_f64((arrival - first_arrival).max(0.) / speed);
+            let at = start + delay.unwrap_or_default();
+            if let Some(wait) = at.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+
+            let packet = rosc::decoder::decode_udp(data).ok().map(|(_, mut packet)| {
+                retime(&mut packet, *arrival, SystemTime::now(), speed);
+                packet
+            });
+
+            emit(packet);
+        }
+    }
+}
+
+// Returns `None` at the end of the file
+fn read_record(file: &mut impl BufRead) -> io::Result<Option<(f64, Vec<u8>)>> {
+    if file.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+
+    let mut arrival = [0u8; 8];
+    file.read_exact(&mut arrival)?;
+    let mut len = [0u8; 4];
+    file.read_exact(&mut len)?;
+
+    let len = u32::from_le_bytes(len) as usize;
+    let mut data = Vec::new();
+    file.take(len as u64).read_to_end(&mut data)?;
+    if data.len() < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(Some((f64::from_le_bytes(arrival), data)))
+}
+
+fn retime(packet: &mut OscPacket, arrival: f64, now: SystemTime, speed: f64) {
+    let OscPacket::Bundle(bundle) = packet else {
+        return;
+    };
+
+    if let Some(time) = timetag_time(bundle.timetag) {
+        let time = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let offset = (time - arrival) / speed;
+
+        let replay_time = Duration::try_from_secs_f64(offset.abs())
+            .ok()
+            .and_then(|duration| {
+                if offset >= 0. {
+                    now.checked_add(duration)
+                } else {
+                    now.checked_sub(duration)
+                }
+            });
+
+        if let Some(replay_time) = replay_time
+            && let Ok(timetag) = OscTime::try_from(replay_time)
+        {
+            bundle.timetag = timetag;
+        }
+    }
+
+    for packet in &mut bundle.content {
+        retime(packet, arrival, now, speed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscMessage, OscType};
+    use std::{fs::OpenOptions, path::PathBuf};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("compwaves-{}-{}.osc", name, std::process::id()))
+    }
+
+    fn encode(addr: &str, value: i32) -> Vec<u8> {
+        rosc::encoder::encode(&OscPacket::Message(OscMessage {
+            addr: addr.to_owned(),
+            args: vec![OscType::Int(value)],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round-trip");
+        let first = encode("/freq", 1);
+        let second = encode("/ctrl", 2);
+
+        let recorder = Recorder::create(&path).unwrap();
+        recorder.record(&first, UNIX_EPOCH + Duration::from_secs(10));
+        recorder.record(&second, UNIX_EPOCH + Duration::from_millis(10500));
+        drop(recorder);
+
+        let recording = Recording::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(recording.records, vec![(10., first), (10.5, second)]);
+    }
+
+    #[test]
+    fn truncated_last_record_is_skipped() {
+        let path = temp_path("truncated");
+        let packet = encode("/freq", 1);
+
+        let recorder = Recorder::create(&path).unwrap();
+        recorder.record(&packet, UNIX_EPOCH + Duration::from_secs(1));
+        drop(recorder);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&2f64.to_le_bytes()).unwrap();
+        file.write_all(&(packet.len() as u32).to_le_bytes())
+            .unwrap();
+        file.write_all(&packet[..packet.len() / 2]).unwrap();
+        drop(file);
+
+        let recording = Recording::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(recording.records, vec![(1., packet)]);
+    }
+}
@@ -0,0 +1,191 @@
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    time::{Duration, SystemTime},
+};
+
+// Seconds between the OSC epoch (1900-01-01) and the UNIX epoch
+const UNIX_OFFSET: u32 = 2_208_988_800;
+// Messages waiting for their timetag beyond this are dropped
+const MAX_SCHEDULED: usize = 65536;
+
+struct ScheduledMessage {
+    due: SystemTime,
+    sequence: u64,
+    msg: OscMessage,
+}
+
+impl PartialEq for ScheduledMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScheduledMessage {}
+
+impl PartialOrd for ScheduledMessage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledMessage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.due, self.sequence).cmp(&(other.due, other.sequence))
+    }
+}
+
+pub(crate) struct Scheduler {
+    queue: BinaryHeap<Reverse<ScheduledMessage>>,
+    sequence: u64,
+    offset: f64,
+    max_lookahead: Duration,
+    clamped: u64,
+    dropped: u64,
+}
+
+impl Scheduler {
+    // `offset` is added to every timetag in seconds and may be negative
+    // to fire earlier, e.g. to compensate for projector latency.
+    // Messages are never held longer than `max_lookahead`, so a sender whose clock
+    // runs ahead of ours delays them by at most that much.
+    pub(crate) fn new(offset: f64, max_lookahead: Duration) -> Self {
+        Scheduler {
+            queue: BinaryHeap::new(),
+            sequence: 0,
+            offset,
+            max_lookahead,
+            clamped: 0,
+            dropped: 0,
+        }
+    }
+
+    // Messages whose due time was moved forward to the lookahead limit
+    pub(crate) fn clamped(&self) -> u64 {
+        self.clamped
+    }
+
+    // Messages dropped because the queue was full
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub(crate) fn schedule_bundle(&mut self, bundle: OscBundle) {
+        self.schedule_packets(bundle.timetag, bundle.content);
+    }
+
+    fn schedule_packets(&mut self, timetag: OscTime, packets: Vec<OscPacket>) {
+        for packet in packets {
+            match packet {
+                OscPacket::Message(msg) => self.schedule(timetag, msg),
+                // A nested bundle must not be executed before its enclosing bundle
+                OscPacket::Bundle(bundle) => {
+                    self.schedule_packets(bundle.timetag.max(timetag), bundle.content)
+                }
+            }
+        }
+    }
+
+    fn schedule(&mut self, timetag: OscTime, msg: OscMessage) {
+        if self.queue.len() >= MAX_SCHEDULED {
+            self.dropped += 1;
+            return;
+        }
+
+        let mut due = self.due_time(timetag);
+        let latest = SystemTime::now() + self.max_lookahead;
+        if due > latest {
+            if self.clamped == 0 {
+                eprintln!(
+                    "Warning: OSC timetag more than {:?} ahead, is the sender's clock ahead of ours?",
+                    self.max_lookahead
+                );
+            }
+            self.clamped += 1;
+            due = latest;
+        }
+
+        // Keep arrival order for messages sharing the same timetag
+        self.sequence += 1;
+        self.queue.push(Reverse(ScheduledMessage {
+            due,
+            sequence: self.sequence,
+            msg,
+        }));
+    }
+
+    // Returns the next message due by `now` along with the time it was due
+    pub(crate) fn pop_due(&mut self, now: SystemTime) -> Option<(SystemTime, OscMessage)> {
+        if self.queue.peek()?.0.due > now {
+            return None;
+        }
+
+        self.queue
+            .pop()
+            .map(|Reverse(scheduled)| (scheduled.due, scheduled.msg))
+    }
+
+    fn due_time(&self, timetag: OscTime) -> SystemTime {
+        // Immediate timetags are dispatched on the next frame
+        let time = timetag_time(timetag).unwrap_or_else(SystemTime::now);
+
+        // An offset that is not a valid duration is ignored rather than panicking
+        let Ok(offset) = Duration::try_from_secs_f64(self.offset.abs()) else {
+            return time;
+        };
+
+        if self.offset >= 0. {
+            time.checked_add(offset)
+        } else {
+            time.checked_sub(offset)
+        }
+        .unwrap_or(time)
+    }
+}
+
+// Returns `None` for timetags before the UNIX epoch, including the special "immediately" value (0, 1)
+pub(super) fn timetag_time(timetag: OscTime) -> Option<SystemTime> {
+    if timetag.seconds < UNIX_OFFSET {
+        None
+    } else {
+        Some(SystemTime::from(timetag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle_at(time: SystemTime) -> OscBundle {
+        OscBundle {
+            timetag: OscTime::try_from(time).unwrap(),
+            content: vec![OscPacket::Message(OscMessage {
+                addr: "/dirt/play".to_owned(),
+                args: Vec::new(),
+            })],
+        }
+    }
+
+    #[test]
+    fn timetags_too_far_ahead_are_clamped() {
+        let mut scheduler = Scheduler::new(0., Duration::from_secs(2));
+        let now = SystemTime::now();
+
+        scheduler.schedule_bundle(bundle_at(now + Duration::from_secs(60)));
+        scheduler.schedule_bundle(bundle_at(now + Duration::from_secs(1)));
+
+        assert_eq!(scheduler.clamped(), 1);
+        assert!(
+            scheduler
+                .pop_due(now + Duration::from_millis(1500))
+                .is_some()
+        );
+        assert!(
+            scheduler
+                .pop_due(now + Duration::from_millis(1500))
+                .is_none()
+        );
+        assert!(scheduler.pop_due(now + Duration::from_secs(3)).is_some());
+    }
+}
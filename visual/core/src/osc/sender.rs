@@ -0,0 +1,63 @@
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, eyre},
+};
+use rosc::{OscMessage, OscPacket, OscType};
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::Arc,
+};
+
+#[derive(Clone)]
+pub struct OscSender {
+    socket: Arc<UdpSocket>,
+    target: SocketAddr,
+}
+
+impl OscSender {
+    pub(crate) fn new(target: &str) -> Result<Self> {
+        let target = target
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| eyre!("Invalid OSC send address {}", target))?;
+        let socket = UdpSocket::bind("0.0.0.0:0").wrap_err("Failed to bind OSC send socket")?;
+
+        Ok(OscSender {
+            socket: Arc::new(socket),
+            target,
+        })
+    }
+
+    pub fn send(&self, msg: OscMessage) -> Result<()> {
+        self.send_packet(self.target, &OscPacket::Message(msg))
+    }
+
+    // Sends to another address than the configured one, e.g. Tidal's controller port
+    pub fn send_to(&self, target: impl ToSocketAddrs, msg: OscMessage) -> Result<()> {
+        let target = target
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| eyre!("Invalid OSC send address"))?;
+        self.send_packet(target, &OscPacket::Message(msg))
+    }
+
+    // Plays a sound through SuperDirt, e.g. `dirt_play(&[("s", "sn".into()), ("n", 3.into())])`
+    pub fn dirt_play(&self, properties: &[(&str, OscType)]) -> Result<()> {
+        let args = properties
+            .iter()
+            .flat_map(|(key, value)| [OscType::String(key.to_string()), value.clone()])
+            .collect();
+
+        self.send(OscMessage {
+            addr: "/dirt/play".to_owned(),
+            args,
+        })
+    }
+
+    fn send_packet(&self, target: SocketAddr, packet: &OscPacket) -> Result<()> {
+        let data = rosc::encoder::encode(packet)?;
+        self.socket.send_to(&data, target)?;
+        Ok(())
+    }
+}
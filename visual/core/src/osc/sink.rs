@@ -0,0 +1,73 @@
+use super::{recording::Recorder, stats::OscCounters};
+use rosc::OscPacket;
+use std::{
+    fmt::Display,
+    sync::{
+        Arc,
+        mpsc::{SyncSender, TrySendError},
+    },
+    time::SystemTime,
+};
+
+// Shared by every packet source to count, record and queue packets for the next frame
+#[derive(Clone)]
+pub(crate) struct PacketSink {
+    sender: SyncSender<OscPacket>,
+    counters: Arc<OscCounters>,
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl PacketSink {
+    pub(crate) fn new(
+        sender: SyncSender<OscPacket>,
+        counters: Arc<OscCounters>,
+        recorder: Option<Arc<Recorder>>,
+    ) -> Self {
+        PacketSink {
+            sender,
+            counters,
+            recorder,
+        }
+    }
+
+    // Returns `false` once the receiving side is gone
+    pub(crate) fn receive(&self, data: &[u8], source: impl Display) -> bool {
+        self.counters.packet_received();
+        if let Some(recorder) = &self.recorder {
+            recorder.record(data, SystemTime::now());
+        }
+
+        match rosc::decoder::decode_udp(data) {
+            Ok((_, packet)) => self.send(packet),
+            Err(e) => {
+                self.counters.decode_failed();
+                eprintln!("Failed to decode OSC packet from {}: {}", source, e);
+                true
+            }
+        }
+    }
+
+    // For sources that decode by themselves, `None` being a packet that failed to decode
+    pub(crate) fn receive_decoded(&self, packet: Option<OscPacket>) -> bool {
+        self.counters.packet_received();
+
+        match packet {
+            Some(packet) => self.send(packet),
+            None => {
+                self.counters.decode_failed();
+                true
+            }
+        }
+    }
+
+    fn send(&self, packet: OscPacket) -> bool {
+        match self.sender.try_send(packet) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.counters.packet_dropped();
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
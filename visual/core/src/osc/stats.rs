@@ -0,0 +1,95 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+// Shared with the receiving threads
+#[derive(Default)]
+pub(crate) struct OscCounters {
+    received: AtomicU64,
+    decode_failures: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl OscCounters {
+    pub(crate) fn packet_received(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn decode_failed(&self) {
+        self.decode_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn packet_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OscStats {
+    pub packets_per_sec: f64,
+    pub total_packets: u64,
+    pub decode_failures: u64,
+    pub dropped_packets: u64,
+    pub unknown_addresses: u64,
+    // Bundled messages held for at most the lookahead instead of their far future timetag
+    pub clamped_timetags: u64,
+    // Bundled messages dropped because too many were waiting for their timetag
+    pub dropped_scheduled: u64,
+}
+
+pub(crate) struct OscStatsTracker {
+    counters: Arc<OscCounters>,
+    unknown_addresses: u64,
+    window_start: Instant,
+    window_start_packets: u64,
+    packets_per_sec: f64,
+}
+
+impl OscStatsTracker {
+    pub(crate) fn new() -> Self {
+        OscStatsTracker {
+            counters: Arc::new(OscCounters::default()),
+            unknown_addresses: 0,
+            window_start: Instant::now(),
+            window_start_packets: 0,
+            packets_per_sec: 0.,
+        }
+    }
+
+    pub(crate) fn counters(&self) -> Arc<OscCounters> {
+        self.counters.clone()
+    }
+
+    pub(crate) fn unknown_address(&mut self) {
+        self.unknown_addresses += 1;
+    }
+
+    pub(crate) fn update(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < RATE_WINDOW {
+            return;
+        }
+
+        let total = self.counters.received.load(Ordering::Relaxed);
+        self.packets_per_sec = (total - self.window_start_packets) as f64 / elapsed.as_secs_f64();
+        self.window_start = Instant::now();
+        self.window_start_packets = total;
+    }
+
+    pub(crate) fn stats(&self) -> OscStats {
+        OscStats {
+            packets_per_sec: self.packets_per_sec,
+            total_packets: self.counters.received.load(Ordering::Relaxed),
+            decode_failures: self.counters.decode_failures.load(Ordering::Relaxed),
+            dropped_packets: self.counters.dropped.load(Ordering::Relaxed),
+            unknown_addresses: self.unknown_addresses,
+            ..OscStats::default()
+        }
+    }
+}
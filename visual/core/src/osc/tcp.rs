@@ -0,0 +1,284 @@
+use super::sink::PacketSink;
+use std::{
+    io::Read,
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+// Larger frames are treated as a broken stream
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TcpFraming {
+    // OSC 1.1 double-END SLIP framing (RFC 1055)
+    Slip,
+    // OSC 1.0 framing, each packet preceded by its size as a big endian u32
+    LengthPrefixed,
+}
+
+struct Deframer {
+    framing: TcpFraming,
+    buffer: Vec<u8>,
+    escaped: bool,
+}
+
+impl Deframer {
+    fn new(framing: TcpFraming) -> Self {
+        Deframer {
+            framing,
+            buffer: Vec::new(),
+            escaped: false,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        match self.framing {
+            TcpFraming::Slip => self.push_slip(data),
+            TcpFraming::LengthPrefixed => self.push_length_prefixed(data),
+        }
+    }
+
+    fn push_slip(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        let mut frames = Vec::new();
+
+        for &byte in data {
+            if self.escaped {
+                self.escaped = false;
+                match byte {
+                    SLIP_ESC_END => self.buffer.push(SLIP_END),
+                    SLIP_ESC_ESC => self.buffer.push(SLIP_ESC),
+                    _ => self.buffer.push(byte),
+                }
+                continue;
+            }
+
+            match byte {
+                // Frames may start with an END too, so empty frames are skipped
+                SLIP_END if !self.buffer.is_empty() => {
+                    frames.push(std::mem::take(&mut self.buffer))
+                }
+                SLIP_END => {}
+                SLIP_ESC => self.escaped = true,
+                _ => self.buffer.push(byte),
+            }
+
+            // A client that never sends END must not grow the buffer without bound
+            if self.buffer.len() > MAX_FRAME_SIZE {
+                return Err(format!(
+                    "frame exceeds {} bytes without an END",
+                    MAX_FRAME_SIZE
+                ));
+            }
+        }
+
+        Ok(frames)
+    }
+
+    fn push_length_prefixed(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        self.buffer.extend_from_slice(data);
+        let mut frames = Vec::new();
+
+        while self.buffer.len() >= 4 {
+            let size = u32::from_be_bytes(self.buffer[..4].try_into().unwrap()) as usize;
+            if size > MAX_FRAME_SIZE {
+                return Err(format!("frame of {} bytes is too large", size));
+            }
+            if self.buffer.len() < 4 + size {
+                break;
+            }
+
+            frames.push(self.buffer[4..4 + size].to_vec());
+            self.buffer.drain(..4 + size);
+        }
+
+        Ok(frames)
+    }
+}
+
+pub(crate) fn accept_thread(listener: TcpListener, framing: TcpFraming, sink: PacketSink) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let sink = sink.clone();
+                thread::spawn(move || client_thread(stream, framing, sink));
+            }
+            Err(e) => eprintln!("Error accepting OSC TCP connection: {}", e),
+        }
+    }
+}
+
+fn client_thread(mut stream: TcpStream, framing: TcpFraming, sink: PacketSink) {
+    let Ok(peer) = stream.peer_addr() else {
+        return;
+    };
+    println!("OSC TCP client connected: {}", peer);
+
+    let mut deframer = Deframer::new(framing);
+    let mut buf = [0u8; 4096];
+
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(size) => match deframer.push(&buf[..size]) {
+                Ok(frames) => {
+                    for frame in frames {
+                        if !sink.receive(&frame, peer) {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Closing OSC TCP connection from {}: {}", peer, e);
+                    break;
+                }
+            },
+            Err(e) => {
+                eprintln!("Error receiving from OSC TCP client {}: {}", peer, e);
+                break;
+            }
+        }
+    }
+
+    println!("OSC TCP client disconnected: {}", peer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscMessage, OscPacket, OscType};
+    use std::{io::Write, sync::mpsc, time::Duration};
+
+    fn packet() -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: "/dirt/play".to_owned(),
+            args: vec![OscType::String("bd".to_owned())],
+        })
+    }
+
+    // Sends `frame` to an `accept_thread` on a loopback port and returns what reaches the sink
+    fn receive_over_loopback(framing: TcpFraming, frame: &[u8]) -> OscPacket {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::sync_channel(16);
+        let sink = PacketSink::new(sender, Default::default(), None);
+        thread::spawn(move || accept_thread(listener, framing, sink));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(frame).unwrap();
+
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap()
+    }
+
+    #[test]
+    fn slip_packets_reach_the_sink() {
+        let data = rosc::encoder::encode(&packet()).unwrap();
+        let mut frame = vec![SLIP_END];
+        for byte in data {
+            match byte {
+                SLIP_END => frame.extend([SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => frame.extend([SLIP_ESC, SLIP_ESC_ESC]),
+                _ => frame.push(byte),
+            }
+        }
+        frame.push(SLIP_END);
+
+        assert_eq!(receive_over_loopback(TcpFraming::Slip, &frame), packet());
+    }
+
+    #[test]
+    fn length_prefixed_packets_reach_the_sink() {
+        let data = rosc::encoder::encode(&packet()).unwrap();
+        let mut frame = (data.len() as u32).to_be_bytes().to_vec();
+        frame.extend(data);
+
+        assert_eq!(
+            receive_over_loopback(TcpFraming::LengthPrefixed, &frame),
+            packet()
+        );
+    }
+
+    #[test]
+    fn slip_unescapes_end_and_esc() {
+        let mut deframer = Deframer::new(TcpFraming::Slip);
+        let frames = deframer
+            .push(&[
+                SLIP_END,
+                1,
+                SLIP_ESC,
+                SLIP_ESC_END,
+                2,
+                SLIP_ESC,
+                SLIP_ESC_ESC,
+                3,
+                SLIP_END,
+            ])
+            .unwrap();
+
+        assert_eq!(frames, vec![vec![1, SLIP_END, 2, SLIP_ESC, 3]]);
+    }
+
+    #[test]
+    fn slip_frame_split_across_reads() {
+        let mut deframer = Deframer::new(TcpFraming::Slip);
+
+        assert!(
+            deframer
+                .push(&[SLIP_END, 1, 2, SLIP_ESC])
+                .unwrap()
+                .is_empty()
+        );
+        let frames = deframer.push(&[SLIP_ESC_END, 3, SLIP_END]).unwrap();
+
+        assert_eq!(frames, vec![vec![1, 2, SLIP_END, 3]]);
+    }
+
+    #[test]
+    fn slip_multiple_frames_in_one_read() {
+        let mut deframer = Deframer::new(TcpFraming::Slip);
+        let frames = deframer
+            .push(&[SLIP_END, 1, SLIP_END, SLIP_END, 2, 3, SLIP_END, 4])
+            .unwrap();
+
+        assert_eq!(frames, vec![vec![1], vec![2, 3]]);
+        assert_eq!(deframer.push(&[SLIP_END]).unwrap(), vec![vec![4]]);
+    }
+
+    #[test]
+    fn slip_rejects_oversized_frame() {
+        let mut deframer = Deframer::new(TcpFraming::Slip);
+
+        assert!(deframer.push(&vec![0; MAX_FRAME_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn length_prefixed_frame_split_across_reads() {
+        let mut deframer = Deframer::new(TcpFraming::LengthPrefixed);
+
+        assert!(deframer.push(&[0, 0]).unwrap().is_empty());
+        assert!(deframer.push(&[0, 3, 1, 2]).unwrap().is_empty());
+        assert_eq!(deframer.push(&[3]).unwrap(), vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn length_prefixed_multiple_frames_in_one_read() {
+        let mut deframer = Deframer::new(TcpFraming::LengthPrefixed);
+        let frames = deframer
+            .push(&[0, 0, 0, 1, 1, 0, 0, 0, 2, 2, 3, 0, 0])
+            .unwrap();
+
+        assert_eq!(frames, vec![vec![1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn length_prefixed_rejects_oversized_prefix() {
+        let mut deframer = Deframer::new(TcpFraming::LengthPrefixed);
+        let size = (MAX_FRAME_SIZE as u32 + 1).to_be_bytes();
+
+        assert!(deframer.push(&size).is_err());
+    }
+}
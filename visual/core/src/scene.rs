@@ -1,14 +1,21 @@
 use crate::{
     Model,
+    clock::Clock,
+    osc::OscSender,
     params::ParamsData,
-    sound::{AudioFile, DirtSound},
+    sound::{AudioFile, DirtEvent, DirtSound, OutputStreamHandle, glob_match},
+    spectrum::{FrequencyRange, Onset},
 };
+use color_eyre::{Result, eyre::bail};
 use nannou::{
     App, Draw,
     event::{Key, Update},
 };
-use rodio::OutputStreamHandle;
+pub use rosc::OscMessage;
+use rosc::address::{Matcher, OscAddress};
+
 use std::{
+    cmp::Reverse,
     path::{Path, PathBuf},
     sync::mpsc,
 };
@@ -18,123 +25,303 @@ pub trait Scene {
     fn key_pressed(&mut self, audio: Option<(&AudioFile, &OutputStreamHandle)>) {}
     fn key_released(&mut self, audio: Option<(&AudioFile, &OutputStreamHandle)>) {}
     fn invoke(&mut self);
+    fn on_dirt_event(&mut self, event: &DirtEvent) {
+        self.invoke();
+    }
+    fn on_onset(&mut self, onset: &Onset) {
+        self.invoke();
+    }
     fn stop(&mut self);
     fn draw(&self, app: &App, model: &Model, draw: &Draw);
-    fn update(&mut self, update: &Update);
+    fn update(&mut self, update: &Update, clock: &Clock);
     fn on_params_update(&mut self, data: ParamsData) {}
+    fn on_osc_message(&mut self, msg: &OscMessage) {}
+    fn on_osc_sender(&mut self, sender: OscSender) {}
 }
 
 #[derive(Default)]
-pub(crate) struct SceneManager(Vec<SceneInstance>);
+pub(crate) struct SceneManager {
+    scenes: Vec<SceneInstance>,
+    active_bank: u32,
+    blackout: bool,
+}
 
 #[allow(unused)]
 impl SceneManager {
     pub(crate) fn new(scenes: Vec<SceneInstance>) -> Self {
-        SceneManager(scenes)
+        SceneManager {
+            scenes,
+            active_bank: 0,
+            blackout: false,
+        }
+    }
+
+    pub(crate) fn set_osc_sender(&mut self, sender: &OscSender) {
+        for scene in &mut self.scenes {
+            scene.instance.on_osc_sender(sender.clone());
+        }
     }
 
     pub(crate) fn add_scene(&mut self, scene: SceneInstance) {
-        self.0.push(scene);
+        self.scenes.push(scene);
+    }
+
+    pub(crate) fn set_active_bank(&mut self, bank: u32) {
+        self.active_bank = bank;
+    }
+
+    pub(crate) fn set_blackout(&mut self, blackout: bool) {
+        self.blackout = blackout;
+    }
+
+    pub(crate) fn iter_mut_by_name(
+        &mut self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &mut SceneInstance> {
+        self.scenes
+            .iter_mut()
+            .filter(move |v| glob_match(pattern, &v.name))
+    }
+
+    // Muted scenes and scenes outside the active bank neither draw nor react to triggers
+    fn is_active(&self, scene: &SceneInstance) -> bool {
+        scene.enabled && scene.bank.is_none_or(|bank| bank == self.active_bank)
     }
 
     pub(crate) fn invoke_all(&mut self) {
-        for scene_instance in &mut self.0 {
+        for scene_instance in &mut self.scenes {
             scene_instance.instance.invoke();
         }
     }
 
     pub(crate) fn stop_all(&mut self) {
-        for scene in &mut self.0 {
+        for scene in &mut self.scenes {
             scene.instance.stop();
         }
     }
 
-    pub(crate) fn update_all(&mut self, update: &Update) {
-        for scene in &mut self.0 {
-            scene.instance.update(update);
+    pub(crate) fn update_all(&mut self, update: &Update, clock: &Clock) {
+        for scene in &mut self.scenes {
+            scene.instance.update(update, clock);
             scene.handle_params_update_event();
         }
     }
 
     pub(crate) fn draw_all(&self, app: &App, model: &Model, draw: &Draw) {
-        for scene in &model.scene_manager.0 {
+        if self.blackout {
+            return;
+        }
+
+        for scene in self.scenes.iter().filter(|v| self.is_active(v)) {
             scene.instance.draw(app, model, draw);
         }
     }
 
-    pub(crate) fn get_by_key(&self, key: Key) -> Option<&SceneInstance> {
-        self.0.iter().find(|v| v.key.contains(&key))
+    pub(crate) fn iter_mut_by_key(&mut self, key: Key) -> impl Iterator<Item = &mut SceneInstance> {
+        let targets = self.dispatch_targets(|v| v.key.contains(&key));
+        self.iter_mut_targets(targets)
     }
 
-    pub(crate) fn get_mut_by_key(&mut self, key: Key) -> Option<&mut SceneInstance> {
-        self.0.iter_mut().find(|v| v.key.contains(&key))
+    pub(crate) fn iter_mut_by_dirt_event(
+        &mut self,
+        event: &DirtEvent,
+    ) -> impl Iterator<Item = &mut SceneInstance> {
+        let targets = self.dispatch_targets(|v| v.dirt_sounds.iter().any(|s| s.matches(event)));
+        self.iter_mut_targets(targets)
     }
 
-    pub(crate) fn get_by_dirt_sound(&self, sound: &DirtSound) -> Option<&SceneInstance> {
-        self.0.iter().find(|v| v.dirt_sounds.contains(sound))
+    pub(crate) fn iter_mut_by_osc_address(
+        &mut self,
+        address: &OscAddress,
+    ) -> impl Iterator<Item = &mut SceneInstance> {
+        let targets = self.dispatch_targets(|v| {
+            v.osc_addresses
+                .iter()
+                .any(|matcher| matcher.match_address(address))
+        });
+        self.iter_mut_targets(targets)
     }
 
-    pub(crate) fn get_mut_by_dirt_sound(
+    pub(crate) fn iter_mut_by_onset(
         &mut self,
-        sound: &DirtSound,
-    ) -> Option<&mut SceneInstance> {
-        self.0.iter_mut().find(|v| v.dirt_sounds.contains(sound))
+        onset: &Onset,
+    ) -> impl Iterator<Item = &mut SceneInstance> {
+        let targets = self.dispatch_targets(|v| v.onsets.contains(&onset.range));
+        self.iter_mut_targets(targets)
+    }
+
+    // Frequency ranges any scene reacts to, so only those are watched for onsets
+    pub(crate) fn onset_ranges(&self) -> Vec<FrequencyRange> {
+        let mut ranges: Vec<FrequencyRange> = Vec::new();
+        for range in self.scenes.iter().flat_map(|v| &v.onsets) {
+            if !ranges.contains(range) {
+                ranges.push(*range);
+            }
+        }
+        ranges
+    }
+
+    // Matching scenes are visited by descending priority, and an exclusive scene
+    // keeps the event from reaching the scenes after it
+    fn dispatch_targets(&self, matches: impl Fn(&SceneInstance) -> bool) -> Vec<usize> {
+        let mut targets: Vec<usize> = (0..self.scenes.len())
+            .filter(|&i| self.is_active(&self.scenes[i]) && matches(&self.scenes[i]))
+            .collect();
+        targets.sort_by_key(|&i| Reverse(self.scenes[i].priority));
+
+        if let Some(position) = targets.iter().position(|&i| self.scenes[i].exclusive) {
+            targets.truncate(position + 1);
+        }
+
+        targets
+    }
+
+    // Yields the scenes in the order of `targets`
+    fn iter_mut_targets(
+        &mut self,
+        targets: Vec<usize>,
+    ) -> impl Iterator<Item = &mut SceneInstance> {
+        let mut scenes: Vec<Option<&mut SceneInstance>> =
+            self.scenes.iter_mut().map(Some).collect();
+        targets.into_iter().filter_map(move |i| scenes[i].take())
     }
 }
 
 pub struct SceneInstance {
     pub(crate) instance: Box<dyn Scene>,
+    pub(crate) name: String,
     pub(crate) key: Vec<Key>,
     pub(crate) dirt_sounds: Vec<DirtSound>,
+    pub(crate) osc_addresses: Vec<Matcher>,
+    pub(crate) onsets: Vec<FrequencyRange>,
     pub(crate) audio_file: Option<AudioFile>,
+    priority: i32,
+    exclusive: bool,
+    enabled: bool,
+    bank: Option<u32>,
+    params: toml::Table,
     params_update_event_rx: Option<mpsc::Receiver<notify::Event>>,
 }
 
 impl SceneInstance {
     fn handle_params_update_event(&mut self) {
-        if let Some(params_update_event_rx) = &self.params_update_event_rx {
-            if let Ok(event) = params_update_event_rx.try_recv() {
-                if event.kind.is_modify() {
-                    if let Some(first_path) = event.paths.first() {
-                        let s = std::fs::read_to_string(first_path).unwrap();
-                        self.instance.on_params_update(ParamsData::new(s));
-                    }
-                }
-            }
+        if let Some(params_update_event_rx) = &self.params_update_event_rx
+            && let Ok(event) = params_update_event_rx.try_recv()
+            && event.kind.is_modify()
+            && let Some(first_path) = event.paths.first()
+        {
+            let s = std::fs::read_to_string(first_path).unwrap();
+            self.load_params(s);
+        }
+    }
+
+    pub(crate) fn load_params(&mut self, content: String) {
+        match content.parse() {
+            Ok(params) => self.params = params,
+            Err(e) => eprintln!("Failed to parse params of scene {}: {}", self.name, e),
+        }
+        self.instance.on_params_update(ParamsData::new(content));
+    }
+
+    // Overrides a single parameter until the params file changes next
+    pub(crate) fn set_param(&mut self, key: &str, value: toml::Value) {
+        self.params.insert(key.to_owned(), value);
+        self.instance
+            .on_params_update(ParamsData::new(self.params.to_string()));
+    }
+
+    // Sets a numeric parameter, rounded when the params file holds an integer there
+    // so that scenes deserializing it into an integer field keep accepting it
+    pub(crate) fn set_number_param(&mut self, key: &str, value: f64) {
+        let value = match self.params.get(key) {
+            Some(toml::Value::Integer(_)) => toml::Value::Integer(value.round() as i64),
+            _ => toml::Value::Float(value),
+        };
+        self.set_param(key, value);
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.instance.stop();
         }
+        self.enabled = enabled;
     }
 }
 
 pub struct SceneBuilder {
     pub(crate) instance: Box<dyn Scene>,
+    name: String,
     pub(crate) params_file_path: Option<PathBuf>,
     pub(crate) params_update_event_rx: Option<mpsc::Receiver<notify::Event>>,
     keys: Vec<Key>,
-    dirt_sound_names: Vec<&'static str>,
+    dirt_sounds: Vec<DirtSound>,
+    osc_addresses: Vec<Matcher>,
+    invalid_osc_addresses: Vec<String>,
+    onsets: Vec<FrequencyRange>,
     audio_file_path: Option<PathBuf>,
     audio_volume: Option<f32>,
+    priority: i32,
+    exclusive: bool,
+    bank: Option<u32>,
 }
 
 impl SceneBuilder {
     pub fn new<SI: Scene + std::default::Default + 'static>() -> Self {
+        // Named after the scene type by default, e.g. "snare" for `Snare`
+        let type_name = std::any::type_name::<SI>();
+        let name = type_name.rsplit("::").next().unwrap_or(type_name);
+
         SceneBuilder {
             instance: Box::new(SI::default()),
+            name: name.to_lowercase(),
             keys: Vec::new(),
             params_file_path: None,
             params_update_event_rx: None,
-            dirt_sound_names: Vec::new(),
+            dirt_sounds: Vec::new(),
+            osc_addresses: Vec::new(),
+            invalid_osc_addresses: Vec::new(),
+            onsets: Vec::new(),
             audio_file_path: None,
             audio_volume: None,
+            priority: 0,
+            exclusive: false,
+            bank: None,
         }
     }
 
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
     pub fn key(mut self, key: Key) -> Self {
         self.keys.push(key);
         self
     }
 
-    pub fn dirt_sound(mut self, name: &'static str) -> Self {
-        self.dirt_sound_names.push(name);
+    pub fn dirt_sound(mut self, sound: impl Into<DirtSound>) -> Self {
+        self.dirt_sounds.push(sound.into());
+        self
+    }
+
+    // Subscribes `Scene::on_osc_message` to an OSC address pattern,
+    // which may use OSC wildcards like "/ctrl/*" or "/track/{1,2}/[a-z]*".
+    // Invalid patterns make `AppConfig::build` fail.
+    pub fn osc_address(mut self, pattern: &str) -> Self {
+        match Matcher::new(pattern) {
+            Ok(matcher) => self.osc_addresses.push(matcher),
+            Err(e) => self
+                .invalid_osc_addresses
+                .push(format!("{} ({})", pattern, e)),
+        }
+        self
+    }
+
+    // Triggers `Scene::on_onset` on spectral onsets within `range`, e.g. kicks in `FrequencyRange::Bass`
+    pub fn onset(mut self, range: FrequencyRange) -> Self {
+        if !self.onsets.contains(&range) {
+            self.onsets.push(range);
+        }
         self
     }
 
@@ -149,18 +336,41 @@ impl SceneBuilder {
         self
     }
 
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn exclusive(mut self) -> Self {
+        self.exclusive = true;
+        self
+    }
+
+    // Scenes in a bank are only active while that bank is selected,
+    // scenes without a bank are always active
+    pub fn bank(mut self, bank: u32) -> Self {
+        self.bank = Some(bank);
+        self
+    }
+
     pub fn param_file(mut self, path: impl AsRef<Path>) -> Self {
         self.params_file_path = Some(path.as_ref().into());
         self
     }
 
-    pub fn build(self, audio_base_path: impl AsRef<Path>) -> SceneInstance {
-        let dirt_sounds = self
-            .dirt_sound_names
-            .into_iter()
-            .map(DirtSound::new)
-            .collect();
+    pub(crate) fn validate(&self) -> Result<()> {
+        if let Some(pattern) = self.invalid_osc_addresses.first() {
+            bail!(
+                "Invalid OSC address pattern for scene {}: {}",
+                self.name,
+                pattern
+            );
+        }
 
+        Ok(())
+    }
+
+    pub fn build(self, audio_base_path: impl AsRef<Path>) -> SceneInstance {
         let audio_file = self.audio_file_path.map(|f| {
             let mut audio_file = AudioFile::new(f);
 
@@ -173,10 +383,75 @@ impl SceneBuilder {
 
         SceneInstance {
             instance: self.instance,
+            name: self.name,
             key: self.keys,
-            dirt_sounds,
+            dirt_sounds: self.dirt_sounds,
+            osc_addresses: self.osc_addresses,
+            onsets: self.onsets,
             audio_file,
+            priority: self.priority,
+            exclusive: self.exclusive,
+            enabled: true,
+            bank: self.bank,
+            params: toml::Table::new(),
             params_update_event_rx: self.params_update_event_rx,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Dummy;
+
+    impl Scene for Dummy {
+        fn invoke(&mut self) {}
+        fn stop(&mut self) {}
+        fn draw(&self, _app: &App, _model: &Model, _draw: &Draw) {}
+        fn update(&mut self, _update: &Update, _clock: &Clock) {}
+    }
+
+    #[test]
+    fn onset_ranges_reach_the_scene_manager() {
+        let scene = SceneBuilder::new::<Dummy>()
+            .onset(FrequencyRange::Bass)
+            .onset(FrequencyRange::High)
+            .onset(FrequencyRange::Bass)
+            .build("");
+        assert_eq!(
+            scene.onsets,
+            vec![FrequencyRange::Bass, FrequencyRange::High]
+        );
+
+        let other = SceneBuilder::new::<Dummy>()
+            .onset(FrequencyRange::High)
+            .onset(FrequencyRange::Sub)
+            .build("");
+        let scene_manager = SceneManager::new(vec![scene, other]);
+
+        assert_eq!(
+            scene_manager.onset_ranges(),
+            vec![
+                FrequencyRange::Bass,
+                FrequencyRange::High,
+                FrequencyRange::Sub
+            ]
+        );
+    }
+
+    #[test]
+    fn number_params_keep_integer_fields_integers() {
+        let mut scene = SceneBuilder::new::<Dummy>().build("");
+        scene.load_params("n = 3\nsize = 0.5".to_owned());
+
+        scene.set_number_param("n", 4.6);
+        scene.set_number_param("size", 2.);
+        scene.set_number_param("new", 1.5);
+
+        assert_eq!(scene.params["n"], toml::Value::Integer(5));
+        assert_eq!(scene.params["size"], toml::Value::Float(2.));
+        assert_eq!(scene.params["new"], toml::Value::Float(1.5));
+    }
+}
@@ -1,24 +1,205 @@
-pub use rodio::OutputStreamHandle;
+mod output;
+
+pub use output::OutputStreamHandle;
+pub use rosc::OscType;
+
+pub(crate) use output::open_output;
 
 use color_eyre::Result;
 use rodio::{Decoder, Source as _};
 use std::{
+    collections::HashMap,
     fs::File,
     io::BufReader,
     path::{Path, PathBuf},
 };
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    fn test(self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Gt => lhs > rhs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    key: String,
+    comparison: Comparison,
+    value: f64,
+}
+
+// Matches dirt events by sound name, where `*` and `?` work as glob wildcards,
+// optionally narrowed down by sample index, orbit and numeric conditions on properties
+#[derive(Debug, Clone, PartialEq)]
 pub struct DirtSound {
     name: String,
+    n: Option<i32>,
+    orbit: Option<i32>,
+    conditions: Vec<Condition>,
 }
 
 impl DirtSound {
-    pub(crate) fn new(name: &str) -> Self {
+    // Accepts a sound name with an optional sample index, e.g. "sn", "sn:3" or "808*"
+    pub fn new(selector: &str) -> Self {
+        let (name, n) = match selector.rsplit_once(':') {
+            Some((name, n)) => match n.parse() {
+                Ok(n) => (name, Some(n)),
+                Err(_) => (selector, None),
+            },
+            None => (selector, None),
+        };
+
         DirtSound {
             name: name.to_owned(),
+            n,
+            orbit: None,
+            conditions: Vec::new(),
         }
     }
+
+    pub fn orbit(mut self, orbit: i32) -> Self {
+        self.orbit = Some(orbit);
+        self
+    }
+
+    pub fn when(mut self, key: &str, comparison: Comparison, value: f64) -> Self {
+        self.conditions.push(Condition {
+            key: key.to_owned(),
+            comparison,
+            value,
+        });
+        self
+    }
+
+    pub(crate) fn matches(&self, event: &DirtEvent) -> bool {
+        glob_match(&self.name, &event.s)
+            && self.n.is_none_or(|n| event.n as i32 == n)
+            && self.orbit.is_none_or(|orbit| event.orbit == orbit)
+            && self.conditions.iter().all(|condition| {
+                event
+                    .value(&condition.key)
+                    .is_some_and(|v| condition.comparison.test(v, condition.value))
+            })
+    }
+}
+
+impl From<&str> for DirtSound {
+    fn from(selector: &str) -> Self {
+        DirtSound::new(selector)
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Position of the last `*` in the pattern and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Debug, Clone)]
+pub struct DirtEvent {
+    pub s: String,
+    pub n: f32,
+    pub gain: f32,
+    pub orbit: i32,
+    pub cycle: Option<f64>,
+    pub cps: Option<f64>,
+    pub delta: Option<f64>,
+    pub speed: f32,
+    pub pan: f32,
+    pub note: Option<f32>,
+    pub cutoff: Option<f32>,
+    pub extras: HashMap<String, OscType>,
+}
+
+impl DirtEvent {
+    // Returns `None` when the properties do not name a sound with `s`
+    pub(crate) fn from_properties(mut properties: HashMap<String, OscType>) -> Option<Self> {
+        let Some(OscType::String(s)) = properties.remove("s") else {
+            return None;
+        };
+
+        let mut number = |key: &str| properties.remove(key).as_ref().and_then(osc_number);
+
+        Some(DirtEvent {
+            s,
+            n: number("n").unwrap_or(0.) as f32,
+            gain: number("gain").unwrap_or(1.) as f32,
+            orbit: number("orbit").unwrap_or(0.) as i32,
+            cycle: number("cycle"),
+            cps: number("cps"),
+            delta: number("delta"),
+            speed: number("speed").unwrap_or(1.) as f32,
+            pan: number("pan").unwrap_or(0.5) as f32,
+            note: number("note").map(|v| v as f32),
+            cutoff: number("cutoff").map(|v| v as f32),
+            extras: properties,
+        })
+    }
+}
+
+impl DirtEvent {
+    pub fn value(&self, key: &str) -> Option<f64> {
+        match key {
+            "n" => Some(self.n as f64),
+            "gain" => Some(self.gain as f64),
+            "orbit" => Some(self.orbit as f64),
+            "cycle" => self.cycle,
+            "cps" => self.cps,
+            "delta" => self.delta,
+            "speed" => Some(self.speed as f64),
+            "pan" => Some(self.pan as f64),
+            "note" => self.note.map(|v| v as f64),
+            "cutoff" => self.cutoff.map(|v| v as f64),
+            _ => self.extras.get(key).and_then(osc_number),
+        }
+    }
+}
+
+pub(crate) fn osc_number(value: &OscType) -> Option<f64> {
+    match *value {
+        OscType::Int(v) => Some(v as f64),
+        OscType::Long(v) => Some(v as f64),
+        OscType::Float(v) => Some(v as f64),
+        OscType::Double(v) => Some(v),
+        _ => None,
+    }
 }
 
 pub struct AudioFile {
@@ -42,7 +223,7 @@ impl PartialEq for AudioFile {
 }
 
 impl AudioFile {
-    pub fn play(&self, audio_handle: &rodio::OutputStreamHandle) -> Result<()> {
+    pub fn play(&self, audio_handle: &OutputStreamHandle) -> Result<()> {
         let file = BufReader::new(File::open(&self.path)?);
         let source = Decoder::new(file)?;
         let source = source.amplify(self.volume);
@@ -0,0 +1,120 @@
+use crate::analyzer::SampleBatch;
+use color_eyre::{Result, eyre::WrapErr};
+use rodio::{OutputStream, PlayError, Source, dynamic_mixer::DynamicMixerController};
+use std::{
+    sync::{Arc, Weak, mpsc::SyncSender},
+    time::Duration,
+};
+
+const CHANNELS: u16 = 2;
+const SAMPLE_RATE: u32 = 44100;
+const TAP_BATCH_FRAMES: usize = 512;
+const TAP_BATCH_SAMPLES: usize = TAP_BATCH_FRAMES * CHANNELS as usize;
+// Silence still analyzed after the mix runs empty, so the spectrum falls back to the floor
+// without drowning out `/freq` in between sounds
+const TAP_SILENCE_FRAMES: usize = SAMPLE_RATE as usize / 4;
+
+// Stands in for rodio's handle of the same name with the same `play_raw`.
+// Everything played through it is mixed in one source before it reaches the device,
+// so the mix can be tapped for analysis.
+#[derive(Clone)]
+pub struct OutputStreamHandle {
+    mixer: Weak<DynamicMixerController<f32>>,
+}
+
+impl OutputStreamHandle {
+    pub fn play_raw<S>(&self, source: S) -> Result<(), PlayError>
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        // The mixer is gone once the output stream is dropped
+        let mixer = self.mixer.upgrade().ok_or(PlayError::NoDevice)?;
+        mixer.add(source);
+        Ok(())
+    }
+}
+
+// Opens the default output device, passing the mix to `tap` if given
+pub(crate) fn open_output(
+    tap: Option<SyncSender<SampleBatch>>,
+) -> Result<(OutputStream, OutputStreamHandle)> {
+    let (stream, handle) =
+        OutputStream::try_default().wrap_err("Failed to open the audio output")?;
+    let (controller, mixer) = rodio::dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
+    handle
+        .play_raw(Tap {
+            inner: mixer,
+            sender: tap,
+            silent_samples: 0,
+            batch: Vec::with_capacity(TAP_BATCH_SAMPLES),
+        })
+        .wrap_err("Failed to start the audio output")?;
+
+    Ok((
+        stream,
+        OutputStreamHandle {
+            mixer: Arc::downgrade(&controller),
+        },
+    ))
+}
+
+// Passes samples through unchanged while copying them to the analyzer in batches.
+// Plays silence while the mix is empty instead of ending, so later sounds are still heard.
+struct Tap<S> {
+    inner: S,
+    sender: Option<SyncSender<SampleBatch>>,
+    silent_samples: usize,
+    batch: Vec<f32>,
+}
+
+impl<S: Source<Item = f32>> Iterator for Tap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = match self.inner.next() {
+            Some(sample) => {
+                self.silent_samples = 0;
+                sample
+            }
+            None => {
+                self.silent_samples += 1;
+                0.
+            }
+        };
+
+        if let Some(sender) = &self.sender
+            && self.silent_samples <= TAP_SILENCE_FRAMES * CHANNELS as usize
+        {
+            self.batch.push(sample);
+            if self.batch.len() >= TAP_BATCH_SAMPLES {
+                SampleBatch::from_interleaved(
+                    self.inner.sample_rate(),
+                    self.inner.channels() as usize,
+                    &self.batch,
+                )
+                .send_from_audio_thread(sender);
+                self.batch.clear();
+            }
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Tap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,265 @@
+mod analysis;
+mod history;
+mod onset;
+
+pub use analysis::{AnalysisSettings, FrequencyRange};
+pub use history::{SpectrumFrame, SpectrumHistory};
+pub use onset::Onset;
+
+pub(crate) use history::DEFAULT_LENGTH as DEFAULT_HISTORY_LENGTH;
+pub(crate) use onset::DEFAULT_SENSITIVITY as DEFAULT_ONSET_SENSITIVITY;
+
+use analysis::Analysis;
+use onset::OnsetDetector;
+use std::time::Instant;
+
+// Frequency range of a band in Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Band {
+    pub fn center(&self) -> f32 {
+        (self.low + self.high) / 2.
+    }
+}
+
+// How the bands of a spectrum split the range between `min` and `max` Hz.
+// `sc/freq.scd` sends equally wide bands up to the Nyquist frequency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BandLayout {
+    Linear { min: f32, max: f32 },
+    Logarithmic { min: f32, max: f32 },
+}
+
+impl Default for BandLayout {
+    fn default() -> Self {
+        BandLayout::Linear {
+            min: 0.,
+            max: 24000.,
+        }
+    }
+}
+
+impl BandLayout {
+    pub fn bands(&self, count: usize) -> Vec<Band> {
+        let edge = |i: usize| {
+            let t = i as f32 / count as f32;
+            match *self {
+                BandLayout::Linear { min, max } => min + (max - min) * t,
+                BandLayout::Logarithmic { min, max } => {
+                    let min = min.max(f32::MIN_POSITIVE);
+                    min * (max / min).powf(t)
+                }
+            }
+        };
+
+        (0..count)
+            .map(|i| Band {
+                low: edge(i),
+                high: edge(i + 1),
+            })
+            .collect()
+    }
+}
+
+const DEFAULT_FLOOR_DB: f32 = -96.;
+const DEFAULT_CEILING_DB: f32 = 0.;
+
+// Latest spectrum frame in dB, one value per band
+pub struct Spectrum {
+    values: Vec<f32>,
+    levels: Vec<f32>,
+    previous_levels: Vec<f32>,
+    bands: Vec<Band>,
+    layout: BandLayout,
+    floor_db: f32,
+    ceiling_db: f32,
+    received: Option<Instant>,
+    analysis: Analysis,
+    history: SpectrumHistory,
+    onset_detectors: Vec<OnsetDetector>,
+    onsets: Vec<Onset>,
+}
+
+impl Spectrum {
+    // `history_length` is in seconds
+    pub(crate) fn new(layout: BandLayout, settings: AnalysisSettings, history_length: f32) -> Self {
+        Spectrum {
+            values: Vec::new(),
+            levels: Vec::new(),
+            previous_levels: Vec::new(),
+            bands: Vec::new(),
+            layout,
+            floor_db: DEFAULT_FLOOR_DB,
+            ceiling_db: DEFAULT_CEILING_DB,
+            received: None,
+            analysis: Analysis::new(settings),
+            history: SpectrumHistory::new(history_length),
+            onset_detectors: Vec::new(),
+            onsets: Vec::new(),
+        }
+    }
+
+    pub(crate) fn watch_onsets(&mut self, range: FrequencyRange, sensitivity: f32) {
+        if !self.onset_detectors.iter().any(|d| d.range() == range) {
+            self.onset_detectors
+                .push(OnsetDetector::new(range, sensitivity));
+        }
+    }
+
+    // Onsets detected in frames received since the last call
+    pub(crate) fn take_onsets(&mut self) -> Vec<Onset> {
+        std::mem::take(&mut self.onsets)
+    }
+
+    // Accepts any number of bands, values are in dB
+    pub(crate) fn set(&mut self, values: impl IntoIterator<Item = f32>) {
+        self.values.clear();
+        self.values.extend(values);
+
+        if self.bands.len() != self.values.len() {
+            self.bands = self.layout.bands(self.values.len());
+        }
+        let now = Instant::now();
+        self.received = Some(now);
+
+        let levels = (0..self.values.len())
+            .map(|i| self.db_to_level(self.values[i]))
+            .collect();
+        self.previous_levels = std::mem::replace(&mut self.levels, levels);
+        self.analysis.on_frame(&self.levels, &self.previous_levels);
+        self.history.push(SpectrumFrame {
+            received: now,
+            values: self.values.clone(),
+            levels: self.levels.clone(),
+        });
+
+        for detector in &mut self.onset_detectors {
+            if let Some(onset) =
+                detector.process(&self.levels, &self.previous_levels, &self.bands, now)
+            {
+                self.onsets.push(onset);
+            }
+        }
+    }
+
+    pub(crate) fn update(&mut self, delta_seconds: f32) {
+        self.analysis.update(&self.levels, delta_seconds);
+    }
+
+    fn db_to_level(&self, db: f32) -> f32 {
+        ((db - self.floor_db) / (self.ceiling_db - self.floor_db)).clamp(0., 1.)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn bands(&self) -> &[Band] {
+        &self.bands
+    }
+
+    pub fn layout(&self) -> BandLayout {
+        self.layout
+    }
+
+    pub fn floor_db(&self) -> f32 {
+        self.floor_db
+    }
+
+    pub fn ceiling_db(&self) -> f32 {
+        self.ceiling_db
+    }
+
+    // dB value of band `i`, the floor for missing bands
+    pub fn db(&self, i: usize) -> f32 {
+        self.values.get(i).copied().unwrap_or(self.floor_db)
+    }
+
+    // Band `i` scaled from the floor..ceiling dB range to 0..1
+    pub fn level(&self, i: usize) -> f32 {
+        self.levels.get(i).copied().unwrap_or(0.)
+    }
+
+    pub fn levels(&self) -> &[f32] {
+        &self.levels
+    }
+
+    // Level of band `i` following rises within the attack time and falls within the release time
+    pub fn smoothed(&self, i: usize) -> f32 {
+        self.analysis.smoothed.get(i).copied().unwrap_or(0.)
+    }
+
+    pub fn smoothed_levels(&self) -> &[f32] {
+        &self.analysis.smoothed
+    }
+
+    // Highest recent level of band `i`, falling at the peak decay rate
+    pub fn peak(&self, i: usize) -> f32 {
+        self.analysis.peaks.get(i).copied().unwrap_or(0.)
+    }
+
+    pub fn peaks(&self) -> &[f32] {
+        &self.analysis.peaks
+    }
+
+    // Smoothed level of band `i` relative to the loudest recent level,
+    // so quiet input still spans the whole 0..1 range
+    pub fn normalized(&self, i: usize) -> f32 {
+        if self.analysis.gain_reference <= f32::EPSILON {
+            return 0.;
+        }
+
+        (self.smoothed(i) / self.analysis.gain_reference).min(1.)
+    }
+
+    // Mean smoothed level of the bands in `range`, e.g. "how loud is the bass right now"
+    pub fn energy(&self, range: FrequencyRange) -> f32 {
+        let indices = range.band_indices(&self.bands);
+        if indices.is_empty() {
+            return 0.;
+        }
+
+        indices.iter().map(|&i| self.smoothed(i)).sum::<f32>() / indices.len() as f32
+    }
+
+    // Power weighted mean frequency of the latest frame in Hz
+    pub fn centroid(&self) -> f32 {
+        let (weighted, total) =
+            self.values
+                .iter()
+                .zip(&self.bands)
+                .fold((0., 0.), |(weighted, total), (&db, band)| {
+                    let power = 10f32.powf(db / 10.);
+                    (weighted + band.center() * power, total + power)
+                });
+
+        if total > 0. { weighted / total } else { 0. }
+    }
+
+    // How much the levels rose since the previous frame, averaged over bands
+    pub fn flux(&self) -> f32 {
+        self.analysis.flux
+    }
+
+    // Recent frames, e.g. for waterfall visuals
+    pub fn history(&self) -> &SpectrumHistory {
+        &self.history
+    }
+
+    // When the latest frame was received, `None` before the first one
+    pub fn received(&self) -> Option<Instant> {
+        self.received
+    }
+}
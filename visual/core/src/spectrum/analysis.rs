@@ -0,0 +1,129 @@
+use super::Band;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalysisSettings {
+    // Smoothing time constants in seconds for rising and falling levels
+    pub attack: f32,
+    pub release: f32,
+    // How fast held peaks fall, in levels per second
+    pub peak_decay: f32,
+    // How fast the auto-gain reference falls back after a loud passage, in levels per second
+    pub gain_decay: f32,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        AnalysisSettings {
+            attack: 0.01,
+            release: 0.15,
+            peak_decay: 0.5,
+            gain_decay: 0.05,
+        }
+    }
+}
+
+// Named frequency ranges in Hz for `Spectrum::energy`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrequencyRange {
+    Sub,
+    Bass,
+    Mid,
+    High,
+    Custom(f32, f32),
+}
+
+impl FrequencyRange {
+    pub fn hz(self) -> (f32, f32) {
+        match self {
+            FrequencyRange::Sub => (20., 60.),
+            FrequencyRange::Bass => (60., 250.),
+            FrequencyRange::Mid => (250., 4000.),
+            FrequencyRange::High => (4000., 20000.),
+            FrequencyRange::Custom(low, high) => (low, high),
+        }
+    }
+
+    // Indices of the bands centered in this range, or of the bands overlapping it
+    // when the spectrum is too coarse for any band to be centered in it
+    pub(crate) fn band_indices(self, bands: &[Band]) -> Vec<usize> {
+        let (low, high) = self.hz();
+
+        let centered: Vec<usize> = (0..bands.len())
+            .filter(|&i| (low..high).contains(&bands[i].center()))
+            .collect();
+        if !centered.is_empty() {
+            return centered;
+        }
+
+        (0..bands.len())
+            .filter(|&i| bands[i].low < high && bands[i].high > low)
+            .collect()
+    }
+}
+
+// Per band state derived from the incoming levels (0..1)
+pub(crate) struct Analysis {
+    pub(crate) settings: AnalysisSettings,
+    pub(crate) smoothed: Vec<f32>,
+    pub(crate) peaks: Vec<f32>,
+    pub(crate) gain_reference: f32,
+    pub(crate) flux: f32,
+}
+
+impl Analysis {
+    pub(crate) fn new(settings: AnalysisSettings) -> Self {
+        Analysis {
+            settings,
+            smoothed: Vec::new(),
+            peaks: Vec::new(),
+            gain_reference: 0.,
+            flux: 0.,
+        }
+    }
+
+    // Called with the levels of every newly received frame and of the frame before
+    pub(crate) fn on_frame(&mut self, levels: &[f32], previous_levels: &[f32]) {
+        if previous_levels.len() != levels.len() {
+            self.smoothed = levels.to_vec();
+            self.peaks = levels.to_vec();
+            self.flux = 0.;
+            return;
+        }
+
+        // Half-wave rectified difference to the previous frame, averaged over bands
+        self.flux = levels
+            .iter()
+            .zip(previous_levels)
+            .map(|(level, previous)| (level - previous).max(0.))
+            .sum::<f32>()
+            / levels.len().max(1) as f32;
+    }
+
+    // Called every frame, `levels` being the latest received ones
+    pub(crate) fn update(&mut self, levels: &[f32], delta_seconds: f32) {
+        if self.smoothed.len() != levels.len() {
+            return;
+        }
+
+        let coefficient = |time: f32| {
+            if time > 0. {
+                1. - (-delta_seconds / time).exp()
+            } else {
+                1.
+            }
+        };
+        let attack = coefficient(self.settings.attack);
+        let release = coefficient(self.settings.release);
+
+        for ((smoothed, peak), &level) in self.smoothed.iter_mut().zip(&mut self.peaks).zip(levels)
+        {
+            let amount = if level > *smoothed { attack } else { release };
+            *smoothed += (level - *smoothed) * amount;
+            *peak = level.max(*peak - self.settings.peak_decay * delta_seconds);
+        }
+
+        let loudest = levels.iter().copied().fold(0., f32::max);
+        self.gain_reference =
+            loudest.max(self.gain_reference - self.settings.gain_decay * delta_seconds);
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+pub(crate) const DEFAULT_LENGTH: f32 = 4.;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumFrame {
+    pub received: Instant,
+    // Per band values in dB and scaled to 0..1, as in `Spectrum::values` and `Spectrum::levels`
+    pub values: Vec<f32>,
+    pub levels: Vec<f32>,
+}
+
+// Frames received within the last `length` seconds, oldest first
+pub struct SpectrumHistory {
+    frames: VecDeque<SpectrumFrame>,
+    length: Duration,
+}
+
+impl SpectrumHistory {
+    pub(crate) fn new(length: f32) -> Self {
+        SpectrumHistory {
+            frames: VecDeque::new(),
+            length: Duration::from_secs_f32(length.max(0.)),
+        }
+    }
+
+    pub(crate) fn push(&mut self, frame: SpectrumFrame) {
+        let oldest = frame.received.checked_sub(self.length);
+        self.frames.push_back(frame);
+
+        if let Some(oldest) = oldest {
+            while self.frames.front().is_some_and(|v| v.received < oldest) {
+                self.frames.pop_front();
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    pub fn frames(&self) -> impl ExactSizeIterator<Item = &SpectrumFrame> {
+        self.frames.iter()
+    }
+
+    pub fn latest(&self) -> Option<&SpectrumFrame> {
+        self.frames.back()
+    }
+
+    // The frame that was the latest one at `time`, `None` if it is older than the history
+    pub fn frame_at(&self, time: Instant) -> Option<&SpectrumFrame> {
+        let index = self.frames.partition_point(|v| v.received <= time);
+        index.checked_sub(1).map(|i| &self.frames[i])
+    }
+
+    // Frames received within `window` before now, oldest first
+    pub fn frames_within(&self, window: Duration) -> impl Iterator<Item = &SpectrumFrame> {
+        let since = Instant::now().checked_sub(window);
+        self.frames
+            .iter()
+            .filter(move |v| since.is_none_or(|since| v.received >= since))
+    }
+
+    // Per band mean level of the frames received within `window` before now.
+    // Frames with a different band count than the latest one are left out.
+    pub fn average(&self, window: Duration) -> Vec<f32> {
+        let Some(latest) = self.latest() else {
+            return Vec::new();
+        };
+
+        let bands = latest.levels.len();
+        let mut sum = vec![0.; bands];
+        let mut count = 0;
+        for frame in self
+            .frames_within(window)
+            .filter(|v| v.levels.len() == bands)
+        {
+            for (total, level) in sum.iter_mut().zip(&frame.levels) {
+                *total += level;
+            }
+            count += 1;
+        }
+
+        if count > 0 {
+            for total in &mut sum {
+                *total /= count as f32;
+            }
+        }
+        sum
+    }
+}
@@ -0,0 +1,95 @@
+use super::{Band, FrequencyRange};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// Number of past flux values the adaptive threshold follows, about half a second of /freq
+const HISTORY_LENGTH: usize = 16;
+// Flux below this never counts as an onset, so noise in silence does not trigger
+const MIN_FLUX: f32 = 0.01;
+const MIN_INTERVAL: Duration = Duration::from_millis(80);
+
+pub(crate) const DEFAULT_SENSITIVITY: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onset {
+    pub range: FrequencyRange,
+    // Flux above the threshold at the time of the onset
+    pub strength: f32,
+}
+
+// Spectral flux of one frequency range compared against an adaptive threshold
+// of its recent mean plus `sensitivity` standard deviations
+pub(crate) struct OnsetDetector {
+    range: FrequencyRange,
+    sensitivity: f32,
+    history: VecDeque<f32>,
+    last_onset: Option<Instant>,
+}
+
+impl OnsetDetector {
+    pub(crate) fn new(range: FrequencyRange, sensitivity: f32) -> Self {
+        OnsetDetector {
+            range,
+            sensitivity,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            last_onset: None,
+        }
+    }
+
+    pub(crate) fn range(&self) -> FrequencyRange {
+        self.range
+    }
+
+    pub(crate) fn process(
+        &mut self,
+        levels: &[f32],
+        previous_levels: &[f32],
+        bands: &[Band],
+        now: Instant,
+    ) -> Option<Onset> {
+        let indices = self.range.band_indices(bands);
+        if indices.is_empty() || levels.len() != previous_levels.len() {
+            return None;
+        }
+
+        let flux = indices
+            .iter()
+            .map(|&i| (levels[i] - previous_levels[i]).max(0.))
+            .sum::<f32>()
+            / indices.len() as f32;
+
+        let threshold = self.threshold();
+
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(flux);
+
+        let ready = self
+            .last_onset
+            .is_none_or(|last| now.duration_since(last) >= MIN_INTERVAL);
+        if flux > threshold && ready {
+            self.last_onset = Some(now);
+            return Some(Onset {
+                range: self.range,
+                strength: flux - threshold,
+            });
+        }
+
+        None
+    }
+
+    fn threshold(&self) -> f32 {
+        if self.history.is_empty() {
+            return f32::INFINITY;
+        }
+
+        let count = self.history.len() as f32;
+        let mean = self.history.iter().sum::<f32>() / count;
+        let variance = self.history.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / count;
+
+        (mean + self.sensitivity * variance.sqrt()).max(MIN_FLUX)
+    }
+}
@@ -0,0 +1,93 @@
+use std::{collections::VecDeque, time::Instant};
+
+pub(crate) const DEFAULT_LENGTH: f32 = 0.1;
+
+// Most recent mono samples in -1..1, oldest first, covering `length` seconds at the latest sample rate
+pub struct Waveform {
+    samples: VecDeque<f32>,
+    sample_rate: u32,
+    length: f32,
+    received: Option<Instant>,
+}
+
+impl Waveform {
+    pub(crate) fn new(length: f32) -> Self {
+        Waveform {
+            samples: VecDeque::new(),
+            sample_rate: 0,
+            length,
+            received: None,
+        }
+    }
+
+    // Samples at a different rate than the buffered ones replace them
+    pub(crate) fn push(&mut self, sample_rate: u32, samples: impl IntoIterator<Item = f32>) {
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.samples.clear();
+        }
+        self.samples.extend(samples);
+        self.received = Some(Instant::now());
+
+        let capacity = (self.length * sample_rate as f32) as usize;
+        if self.samples.len() > capacity {
+            let excess = self.samples.len() - capacity;
+            self.samples.drain(..excess);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Seconds of signal currently buffered
+    pub fn duration(&self) -> f32 {
+        if self.sample_rate == 0 {
+            return 0.;
+        }
+
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+
+    // Sample `i` counted from the oldest, 0 for missing samples
+    pub fn sample(&self, i: usize) -> f32 {
+        self.samples.get(i).copied().unwrap_or(0.)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    // The last `count` samples, oldest first
+    pub fn latest(&self, count: usize) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(count))
+            .copied()
+    }
+
+    pub fn rms(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.;
+        }
+
+        (self.samples.iter().map(|v| v * v).sum::<f32>() / self.samples.len() as f32).sqrt()
+    }
+
+    pub fn peak(&self) -> f32 {
+        self.samples.iter().fold(0., |peak, v| v.abs().max(peak))
+    }
+
+    // When the latest samples were received, `None` before the first ones
+    pub fn received(&self) -> Option<Instant> {
+        self.received
+    }
+}